@@ -42,6 +42,126 @@ pub enum PoolError {
     #[error("Invalid Tokem Account")]
     InvalidTokenAccount,
 
+    #[error("Fund pool still holds investor funds")]
+    PoolNotEmpty,
+
+    #[error("Investor is not registered in the fund pool")]
+    InvestorNotFound,
+
+    #[error("Invalid manager signers")]
+    InvalidManagerSigners,
+
+    #[error("Not enough manager signers")]
+    NotEnoughManagerSigners,
+
+    #[error("Invalid program config account")]
+    InvalidConfigAccount,
+
+    #[error("Signer is not the program admin")]
+    InvalidAdminAccount,
+
+    #[error("Fund pool parameters are out of the allowed range")]
+    InvalidPoolParameters,
+
+    #[error("Program is paused")]
+    ProgramPaused,
+
+    #[error("Invalid treasury account")]
+    InvalidTreasuryAccount,
+
+    #[error("Fund pool is paused")]
+    PoolPaused,
+
+    #[error("Fund pool is finalized")]
+    PoolFinalized,
+
+    #[error("Token mint decimals don't match the fund pool")]
+    DecimalsMismatch,
+
+    #[error("The raise of the fund pool is closed")]
+    RaiseClosed,
+
+    #[error("Vesting can't be changed once the pool has investors")]
+    VestingLocked,
+
+    #[error("Invalid vesting account")]
+    InvalidVestingAccount,
+
+    #[error("The fund pool's tokens are not freezable")]
+    PoolNotFreezable,
+
+    #[error("Invalid order account")]
+    InvalidOrderAccount,
+
+    #[error("Invalid order parameters")]
+    InvalidOrderParameters,
+
+    #[error("The investor's position is too small")]
+    InsufficientPosition,
+
+    #[error("Invalid investment account")]
+    InvalidInvestmentAccount,
+
+    #[error("The investor's token count is below the minimum")]
+    BelowMinInvestment,
+
+    #[error("The investor's token count is above the maximum")]
+    AboveMaxInvestment,
+
+    #[error("Not enough tokens left in the fund pool")]
+    NotEnoughTokensLeft,
+
+    #[error("Invalid whitelist tier")]
+    InvalidWhitelistTier,
+
+    #[error("Invalid whitelist account")]
+    InvalidWhitelistAccount,
+
+    #[error("The investor is not in the fund pool's allowlist")]
+    NotAllowlisted,
+
+    #[error("The investor's token count is above its allocation")]
+    AboveAllocation,
+
+    #[error("Invalid or expired attestation")]
+    InvalidAttestation,
+
+    #[error("The pricing can't be changed once tokens are sold")]
+    PricingLocked,
+
+    #[error("The price is above the max amount")]
+    PriceAboveMax,
+
+    #[error("The instruction has expired")]
+    InstructionExpired,
+
+    #[error("Not available in the fund pool's pricing mode")]
+    InvalidPricingMode,
+
+    #[error("The auction is not active")]
+    AuctionNotActive,
+
+    #[error("The auction is not settled yet")]
+    AuctionNotSettled,
+
+    #[error("Invalid bid account")]
+    InvalidBidAccount,
+
+    #[error("Invalid pool pda")]
+    InvalidPoolPda,
+
+    #[error("Invalid referral account")]
+    InvalidReferralAccount,
+
+    #[error("Invalid oracle account")]
+    InvalidOracleAccount,
+
+    #[error("Oracle price is stale")]
+    StalePrice,
+
+    #[error("Oracle price confidence is too wide")]
+    PriceUncertain,
+
 }
 
 impl From<PoolError> for ProgramError {
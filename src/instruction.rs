@@ -3,12 +3,14 @@
  */
 
 use crate::{error::PoolError};
-use crate::state::{unpack_bool}; 
+use crate::state::{unpack_bool, MintMode, VestingSchedule, OrderSide, WhitelistTier, PricingMode, PriceTier}; 
+use num_enum::TryFromPrimitive;
 
 use solana_program::{
     program_error::ProgramError,
     pubkey::{Pubkey, PUBKEY_BYTES},
     clock::{Clock},
+    keccak::HASH_BYTES,
     sysvar::Sysvar, 
   
 };
@@ -56,8 +58,212 @@ pub enum PoolInstruction {
     },
 
 
+    /// Like CreateFundPool, but the program creates the pool's
+    /// mint and token vault at PDAs and keeps the mint authority,
+    /// the mint mode is either Program (pre-minted) or Lazy.
+    /// A freezable pool also makes the token_pda the freeze authority 
+    /// and freezes the investors' token accounts after purchase
+    CreateFundPoolWithMint {
+
+        manager : Pubkey,
+
+        address : Pubkey, 
+
+        fee_in_lamports : u64,
+
+        token_count : u64, 
+
+        token_to_lamport_ratio : u64, 
+
+        is_finalized : bool,
+
+        icon : u16, 
+
+        decimals : u8,
+
+        mint_mode : MintMode,
+
+        is_freezable : bool,
+    },
+
+
     DeleteFundPool ,
 
+    /// Refunds the investors passed in the accounts from the pool_pda, 
+    /// without their signatures, so a pool holding investor funds can 
+    /// later be deleted. The signer count is the number of manager 
+    /// (or admin) signers before the investors' accounts
+    WindDownFundPool {
+
+        signer_count : u8,
+    },
+
+    /// Proposes a new manager, Pubkey::default() cancels the proposal
+    ProposeManager {
+
+        new_manager : Pubkey,
+    },
+
+    /// The proposed manager accepts and takes over the pool
+    AcceptManager ,
+
+    /// Sets the M-of-N manager set, a zero threshold with
+    /// no signers falls back to the single manager
+    SetManagerSigners {
+
+        threshold : u8,
+
+        signers : Vec<Pubkey>,
+    },
+
+    /// Pauses or resumes new investments into the pool
+    SetPoolPaused {
+
+        is_paused : bool,
+    },
+
+    /// Marks the pool finalized and revokes the token mint authority
+    FinalizeFundPool ,
+
+    /// Ends the raise, burns the unsold tokens left in the vault
+    CloseRaise ,
+
+    /// Sets the vesting of the purchased tokens, 
+    /// only before the pool has any investor
+    SetVesting {
+
+        schedule : VestingSchedule,
+
+        start : i64,
+
+        cliff : i64,
+
+        duration : i64,
+    },
+
+    /// Sets the limits of each investor's total token count 
+    /// and the whitelist tiers overriding them
+    SetInvestorLimits {
+
+        min_tokens : u64,
+
+        max_tokens : u64,
+
+        tiers : Vec<WhitelistTier>,
+    },
+
+    /// Puts the investor in a whitelist tier, tier 0 removes 
+    /// the investor from the tiers
+    SetWhitelistTier {
+
+        investor : Pubkey,
+
+        tier : u8,
+    },
+
+    /// Sets the pricing mode, only before any token is sold
+    SetPricing {
+
+        mode : PricingMode,
+
+        curve_param : u64,
+    },
+
+    /// Sets the price tiers of the tiered pricing modes, 
+    /// only before any token is sold
+    SetPriceTiers {
+
+        tiers : Vec<PriceTier>,
+    },
+
+    /// Turns the raise into a Dutch auction, only before any token is sold
+    SetAuction {
+
+        start_price : u64,
+
+        floor_price : u64,
+
+        start : i64,
+
+        end : i64,
+    },
+
+    /// Settles an undersold auction at the floor price once it has ended
+    SettleAuction,
+
+    /// Prices the tokens in micro-USD paid in lamports at the oracle's 
+    /// SOL/USD price, only before any token is sold.
+    /// The feed must be owned by the oracle program
+    SetOraclePricing {
+
+        oracle : Pubkey,
+
+        oracle_program : Pubkey,
+
+        usd_price : u64,
+
+        max_age : i64,
+
+        max_conf_bps : u16,
+    },
+
+    /// Copies a pool of the original layout into a new account of 
+    /// the current one and moves the pool_pda's lamports to the new 
+    /// pool_pda, the original account is closed to the manager
+    MigrateFundPool,
+
+    /// Sets the referrer's share of the manager's fee, in basis points
+    SetReferralBps {
+
+        referral_bps : u16,
+    },
+
+    /// Rotates the allowlist root, all zeros opens the pool to anyone
+    SetAllowlistRoot {
+
+        root : [u8; HASH_BYTES],
+    },
+
+    /// Sets the verifier whose attestations the investors must present,
+    /// Pubkey::default() removes the requirement
+    SetRequiredVerifier {
+
+        verifier : Pubkey,
+    },
+
+    /// Freezes or thaws a holder's token account of a freezable pool
+    SetHolderFrozen {
+
+        is_frozen : bool,
+    },
+
+    /// Places a limit order of the pool tokens, a sell escrows the tokens
+    /// and a buy escrows the lamports of token_count * price
+    /// The allowlist proof is the buyer's, when the pool has an allowlist
+    PlaceOrder {
+
+        side : OrderSide,
+
+        token_count : u64,
+
+        price : u64,
+
+        allowlist : AllowlistProof,
+    },
+
+    /// Returns what's left in the escrow to the order's owner
+    CancelOrder,
+
+    /// Fills some or all of the order at its price, the allowlist 
+    /// proof is the buying wallet's, when the pool has an allowlist.
+    /// A fully filled order is closed to its maker
+    FillOrder {
+
+        token_count : u64,
+
+        allowlist : AllowlistProof,
+    },
+
 
     AddInvestor {
         investor : Pubkey, 
@@ -66,12 +272,51 @@ pub enum PoolInstruction {
     
         address : Pubkey,
     
+        // the most lamports to pay for the token count
         amount : u64, 
 
         token_count : u64,
       
         date : i64, 
 
+        // the unix timestamp after which the instruction is 
+        // rejected, zero is no expiry
+        expiry : i64,
+
+        allowlist : AllowlistProof,
+
+    },
+
+    /// Copies an investor record of the original layout into a new account
+    /// of the current one, the original record is closed to the investor
+    MigrateInvestor,
+
+    /// Releases the unlocked part of the investor's vested tokens,
+    /// the investor account's vesting token count follows
+    ClaimVested ,
+
+    /// Bids for the token count at the current auction price,
+    /// the lamports are locked in the pool_pda
+    /// The allowlist proof is the bidder's, when the pool has an allowlist
+    PlaceBid {
+
+        token_count : u64,
+
+        allowlist : AllowlistProof,
+    },
+
+    /// Claims the tokens of the bids at the clearing price 
+    /// and the refund of the excess lamports
+    ClaimBid,
+
+    /// Moves some or all of the signer's position to another wallet,
+    /// with the investor records, the registry and the tokens, the 
+    /// allowlist proof is the recipient's, when the pool has an allowlist
+    TransferPosition {
+
+        token_count : u64,
+
+        allowlist : AllowlistProof,
     },
 
     CreateMarket{
@@ -87,7 +332,62 @@ pub enum PoolInstruction {
     DeleteFromMarket{
 
         fund_pool : Pubkey, 
-    }
+    },
+
+    /// The verifier issues or renews the attestation of the wallet
+    IssueAttestation {
+
+        wallet : Pubkey,
+
+        jurisdiction : u16,
+
+        expiry : i64,
+    },
+
+    /// The verifier revokes the attestation, closing its account
+    RevokeAttestation,
+
+    InitProgramConfig {
+
+        config : ProgramConfigData,
+    },
+
+    UpdateProgramConfig {
+
+        config : ProgramConfigData,
+    },
+}
+
+
+/// The investor's leaf of the allowlist and its Merkle proof, 
+/// a zero allocation is no cap
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllowlistProof {
+
+    pub allocation : u64,
+
+    pub proof : Vec<[u8; HASH_BYTES]>,
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramConfigData {
+
+    pub admin : Pubkey,
+
+    pub treasury : Pubkey,
+
+    pub protocol_fee_bps : u16,
+
+    pub is_paused : bool,
+
+    pub min_token_count : u64,
+
+    pub max_token_count : u64,
+
+    pub min_token_to_lamport_ratio : u64,
+
+    pub max_fee_in_lamports : u64,
 }
 
 
@@ -97,6 +397,12 @@ const MODULE_INVESTOR : u8 = 2;
 
 const MODULE_MARKET : u8 = 3;
 
+const MODULE_CONFIG : u8 = 4;
+
+const MODULE_ORDER : u8 = 5;
+
+const MODULE_ATTESTATION : u8 = 6;
+
 
 impl PoolInstruction {
 
@@ -115,6 +421,12 @@ impl PoolInstruction {
             &MODULE_INVESTOR => Self::unpack_investor(rest)?,
          
             &MODULE_MARKET => Self::unpack_market(rest)?,
+
+            &MODULE_CONFIG => Self::unpack_config(rest)?,
+
+            &MODULE_ORDER => Self::unpack_order(rest)?,
+
+            &MODULE_ATTESTATION => Self::unpack_attestation(rest)?,
            
             _ => return Err(PoolError::InvalidModule.into()),
 
@@ -132,6 +444,58 @@ const ACTION_REGISTER : u8 = 3;
 
 const ACTION_DELETE : u8 = 44;
 
+const ACTION_WIND_DOWN : u8 = 45;
+
+const ACTION_PROPOSE_MANAGER : u8 = 46;
+
+const ACTION_ACCEPT_MANAGER : u8 = 47;
+
+const ACTION_SET_MANAGER_SIGNERS : u8 = 48;
+
+const ACTION_SET_PAUSED : u8 = 49;
+
+const ACTION_FINALIZE : u8 = 50;
+
+const ACTION_CREATE_WITH_MINT : u8 = 51;
+
+const ACTION_CLOSE_RAISE : u8 = 52;
+
+const ACTION_SET_VESTING : u8 = 53;
+
+const ACTION_CLAIM_VESTED : u8 = 54;
+
+const ACTION_SET_HOLDER_FROZEN : u8 = 55;
+
+const ACTION_FILL : u8 = 56;
+
+const ACTION_TRANSFER_POSITION : u8 = 57;
+
+const ACTION_SET_INVESTOR_LIMITS : u8 = 58;
+
+const ACTION_SET_WHITELIST_TIER : u8 = 59;
+
+const ACTION_SET_ALLOWLIST_ROOT : u8 = 60;
+
+const ACTION_SET_REQUIRED_VERIFIER : u8 = 61;
+
+const ACTION_SET_PRICING : u8 = 62;
+
+const ACTION_SET_PRICE_TIERS : u8 = 63;
+
+const ACTION_SET_AUCTION : u8 = 64;
+
+const ACTION_BID : u8 = 65;
+
+const ACTION_SETTLE_AUCTION : u8 = 66;
+
+const ACTION_CLAIM_BID : u8 = 67;
+
+const ACTION_SET_REFERRAL_BPS : u8 = 68;
+
+const ACTION_SET_ORACLE_PRICING : u8 = 69;
+
+const ACTION_MIGRATE : u8 = 70;
+
 impl PoolInstruction {
 
     fn unpack_market(input : &[u8])-> Result<Self, ProgramError>{
@@ -180,6 +544,115 @@ impl PoolInstruction {
     }
 }
 
+impl PoolInstruction {
+
+    fn unpack_order(input : &[u8])-> Result<Self, ProgramError>{
+
+        let (action,rest) = input.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+        Ok(match action  {
+
+            &ACTION_CREATE => {
+
+                const L : usize = 17;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (side, token_count, price) = array_refs![output, 1, 8, 8];
+
+                Self::PlaceOrder {
+                    side : OrderSide::try_from_primitive(side[0])
+                    .map_err(|_| PoolError::InvalidInstruction)?,
+                    token_count : u64::from_le_bytes(*token_count),
+                    price : u64::from_le_bytes(*price),
+                    allowlist : unpack_allowlist_proof(&rest[L..])?,
+                }
+            },
+
+            &ACTION_DELETE => Self::CancelOrder,
+
+            &ACTION_FILL => {
+
+                if rest.len() < 8 {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, 8];
+
+                Self::FillOrder {
+                    token_count : u64::from_le_bytes(*output),
+                    allowlist : unpack_allowlist_proof(&rest[8..])?,
+                }
+            },
+
+            _ => return Err(PoolError::InvalidAction.into()),
+
+        })
+    }
+}
+
+impl PoolInstruction {
+
+    fn unpack_attestation(input : &[u8])-> Result<Self, ProgramError>{
+
+        let (action,rest) = input.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+        Ok(match action  {
+
+            &ACTION_CREATE => {
+
+                const L : usize = PUBKEY_BYTES + 2 + 8;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (wallet, jurisdiction, expiry) = array_refs![output, PUBKEY_BYTES, 2, 8];
+
+                Self::IssueAttestation {
+                    wallet : unpack_pub_key(wallet),
+                    jurisdiction : u16::from_le_bytes(*jurisdiction),
+                    expiry : i64::from_le_bytes(*expiry),
+                }
+            },
+
+            &ACTION_DELETE => Self::RevokeAttestation,
+
+            _ => return Err(PoolError::InvalidAction.into()),
+
+        })
+    }
+}
+
+impl PoolInstruction {
+
+    fn unpack_config(input : &[u8])-> Result<Self, ProgramError>{
+
+        let (action,rest) = input.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+        Ok(match action  {
+
+            &ACTION_CREATE => Self::InitProgramConfig {
+                config : unpack_config_data(rest)?,
+            },
+
+            &ACTION_UPDATE => Self::UpdateProgramConfig {
+                config : unpack_config_data(rest)?,
+            },
+
+            _ => return Err(PoolError::InvalidAction.into()),
+
+        })
+    }
+}
+
 impl PoolInstruction{
 
     fn unpack_fund_pool(input : &[u8])-> Result<Self, ProgramError>{
@@ -192,7 +665,7 @@ impl PoolInstruction{
             &ACTION_CREATE => {
 
                 let (manager,address, lamports, token_count, token_to_lamport_ratio, is_finalized, icon ) = 
-                unpack_fund_pool_data(&rest);
+                unpack_fund_pool_data(&rest)?;
 
                 Self::CreateFundPool{
 
@@ -211,7 +684,7 @@ impl PoolInstruction{
             &ACTION_UPDATE => {
 
                 let (manager,address, lamports, token_count, token_to_lamport_ratio, is_finalized, icon ) = 
-                unpack_fund_pool_data(&rest);
+                unpack_fund_pool_data(&rest)?;
 
                 Self::UpdateFundPool{ 
 
@@ -227,7 +700,292 @@ impl PoolInstruction{
    
             },
 
+            &ACTION_CREATE_WITH_MINT => {
+
+                let (manager,address, lamports, token_count, token_to_lamport_ratio, is_finalized, icon ) = 
+                unpack_fund_pool_data(rest)?;
+
+                let decimals = *rest.get(FUND_POOL_DATA_LEN).ok_or(PoolError::InvalidInstruction)?;
+
+                let mint_mode = rest.get(FUND_POOL_DATA_LEN + 1)
+                .and_then(|m| MintMode::try_from_primitive(*m).ok())
+                .ok_or(PoolError::InvalidInstruction)?;
+
+                if mint_mode == MintMode::Client {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                // optional, the pools created before default to not freezable
+                let is_freezable = match rest.get(FUND_POOL_DATA_LEN + 2) {
+                    Some(b) => unpack_bool(&[*b])?,
+                    None => false, 
+                };
+
+                Self::CreateFundPoolWithMint{
+
+                    manager,
+                    address,
+                    fee_in_lamports : lamports,
+                    token_count,
+                    token_to_lamport_ratio, 
+                    is_finalized,
+                    icon,
+                    decimals,
+                    mint_mode,
+                    is_freezable,
+                }
+
+            },
+
             &ACTION_DELETE => Self::DeleteFundPool,
+
+            &ACTION_WIND_DOWN => {
+
+                let (signer_count, _) = rest.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+                Self::WindDownFundPool {
+                    signer_count : *signer_count,
+                }
+            },
+
+            &ACTION_PROPOSE_MANAGER => {
+
+                const L : usize = PUBKEY_BYTES;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (new_manager,_) = array_refs![output, PUBKEY_BYTES, 0 ];
+
+                Self::ProposeManager {
+                    new_manager : unpack_pub_key(new_manager),
+                }
+            },
+
+            &ACTION_ACCEPT_MANAGER => Self::AcceptManager,
+
+            &ACTION_SET_MANAGER_SIGNERS => {
+
+                let (threshold, signers) = unpack_manager_signers_data(rest)?;
+
+                Self::SetManagerSigners { threshold, signers }
+            },
+
+            &ACTION_SET_PAUSED => {
+
+                const L : usize = 1;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+
+                Self::SetPoolPaused {
+                    is_paused : unpack_bool(output)?,
+                }
+            },
+
+            &ACTION_FINALIZE => Self::FinalizeFundPool,
+
+            &ACTION_CLOSE_RAISE => Self::CloseRaise,
+
+            &ACTION_SET_VESTING => {
+
+                const L : usize = 25;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (schedule, start, cliff, duration) = array_refs![output, 1, 8, 8, 8];
+
+                Self::SetVesting {
+                    schedule : VestingSchedule::try_from_primitive(schedule[0])
+                    .map_err(|_| PoolError::InvalidInstruction)?,
+                    start : i64::from_le_bytes(*start),
+                    cliff : i64::from_le_bytes(*cliff),
+                    duration : i64::from_le_bytes(*duration),
+                }
+            },
+
+            &ACTION_SET_INVESTOR_LIMITS => {
+
+                let (min_tokens, max_tokens, tiers) = unpack_investor_limits_data(rest)?;
+
+                Self::SetInvestorLimits { min_tokens, max_tokens, tiers }
+            },
+
+            &ACTION_SET_WHITELIST_TIER => {
+
+                const L : usize = PUBKEY_BYTES + 1;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (investor, tier) = array_refs![output, PUBKEY_BYTES, 1];
+
+                Self::SetWhitelistTier {
+                    investor : unpack_pub_key(investor),
+                    tier : tier[0],
+                }
+            },
+
+            &ACTION_SET_ALLOWLIST_ROOT => {
+
+                if rest.len() < HASH_BYTES {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                Self::SetAllowlistRoot {
+                    root : *array_ref![rest, 0, HASH_BYTES],
+                }
+            },
+
+            &ACTION_SET_PRICING => {
+
+                const L : usize = 9;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (mode, curve_param) = array_refs![output, 1, 8];
+
+                Self::SetPricing {
+                    mode : PricingMode::try_from_primitive(mode[0])
+                    .map_err(|_| PoolError::InvalidInstruction)?,
+                    curve_param : u64::from_le_bytes(*curve_param),
+                }
+            },
+
+            &ACTION_SET_AUCTION => {
+
+                const L : usize = 32;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (start_price, floor_price, start, end) = array_refs![output, 8, 8, 8, 8];
+
+                Self::SetAuction {
+                    start_price : u64::from_le_bytes(*start_price),
+                    floor_price : u64::from_le_bytes(*floor_price),
+                    start : i64::from_le_bytes(*start),
+                    end : i64::from_le_bytes(*end),
+                }
+            },
+
+            &ACTION_SETTLE_AUCTION => Self::SettleAuction,
+
+            &ACTION_MIGRATE => Self::MigrateFundPool,
+
+            &ACTION_SET_ORACLE_PRICING => {
+
+                const L : usize = 82;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+                let (oracle, oracle_program, usd_price, max_age, max_conf_bps) = array_refs![output, 32, 32, 8, 8, 2];
+
+                Self::SetOraclePricing {
+                    oracle : Pubkey::new_from_array(*oracle),
+                    oracle_program : Pubkey::new_from_array(*oracle_program),
+                    usd_price : u64::from_le_bytes(*usd_price),
+                    max_age : i64::from_le_bytes(*max_age),
+                    max_conf_bps : u16::from_le_bytes(*max_conf_bps),
+                }
+            },
+
+            &ACTION_SET_REFERRAL_BPS => {
+
+                const L : usize = 2;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+
+                Self::SetReferralBps {
+                    referral_bps : u16::from_le_bytes(*output),
+                }
+            },
+
+            &ACTION_SET_PRICE_TIERS => {
+
+                let (count, rest) = rest.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+                let count = *count as usize;
+
+                if rest.len() < count * 16 {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let tiers = rest.chunks(16).take(count).map(|t| {
+
+                    let t = array_ref![t, 0, 16];
+                    let (threshold, price) = array_refs![t, 8, 8];
+
+                    PriceTier {
+                        threshold : u64::from_le_bytes(*threshold),
+                        price : u64::from_le_bytes(*price),
+                    }
+
+                }).collect();
+
+                Self::SetPriceTiers { tiers }
+            },
+
+            &ACTION_SET_REQUIRED_VERIFIER => {
+
+                if rest.len() < PUBKEY_BYTES {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                Self::SetRequiredVerifier {
+                    verifier : unpack_pub_key(&rest[..PUBKEY_BYTES]),
+                }
+            },
+
+            &ACTION_SET_HOLDER_FROZEN => {
+
+                const L : usize = 1;
+
+                if rest.len() < L {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, L];
+
+                Self::SetHolderFrozen {
+                    is_frozen : unpack_bool(output)?,
+                }
+            },
             
             _ => return Err(PoolError::InvalidAction.into()),
 
@@ -253,7 +1011,7 @@ impl PoolInstruction {
                     amount, 
                     token_count,
                     date, 
-                ) =  unpack_investor_data(&rest);
+                ) =  unpack_investor_data(rest)?;
 
                 Self::AddInvestor{
 
@@ -264,10 +1022,49 @@ impl PoolInstruction {
                     amount : amount, 
                     token_count : token_count,
                     date : date , 
+                    expiry : unpack_add_investor_expiry(rest)?
+                    .map(|e| i64::from_le_bytes(*array_ref![e, 0, 8])).unwrap_or(0),
+                    allowlist : unpack_allowlist_proof(rest.get(INVESTOR_DATA_LEN + 8..).unwrap_or(&[]))?,
                 }
 
             },
 
+            &ACTION_CLAIM_VESTED => Self::ClaimVested,
+
+            &ACTION_MIGRATE => Self::MigrateInvestor,
+
+            &ACTION_BID => {
+
+                if rest.len() < 8 {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, 8];
+
+                Self::PlaceBid {
+                    token_count : u64::from_le_bytes(*output),
+                    allowlist : unpack_allowlist_proof(&rest[8..])?,
+                }
+            },
+
+            &ACTION_CLAIM_BID => Self::ClaimBid,
+
+            &ACTION_TRANSFER_POSITION => {
+
+                if rest.len() < 8 {
+
+                    return Err(PoolError::InvalidInstruction.into());
+                }
+
+                let output = array_ref![rest, 0, 8];
+
+                Self::TransferPosition {
+                    token_count : u64::from_le_bytes(*output),
+                    allowlist : unpack_allowlist_proof(&rest[8..])?,
+                }
+            },
+
             _ => return Err(PoolError::InvalidAction.into()),
 
         })
@@ -276,9 +1073,20 @@ impl PoolInstruction {
 }
 
 
-fn unpack_investor_data(input : &[u8]) -> (Pubkey, Pubkey, Pubkey, u64, u64, i64){
+const INVESTOR_DATA_LEN : usize = 112;
+
+// investor, pool_address, address, amount, token_count and the current time as the date
+type InvestorData = (Pubkey, Pubkey, Pubkey, u64, u64, i64);
+
+fn unpack_investor_data(input : &[u8]) -> Result<InvestorData, ProgramError>{
+
+    const L : usize = INVESTOR_DATA_LEN; 
+
+    if input.len() < L {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
 
-    const L : usize = 112; 
     let output = array_ref![input, 0, L];
     let (
         investor, 
@@ -289,40 +1097,202 @@ fn unpack_investor_data(input : &[u8]) -> (Pubkey, Pubkey, Pubkey, u64, u64, i64
     ) = 
     array_refs![output, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES,8,8 ];
 
-    let currtime =  Clock::get().unwrap().unix_timestamp;
+    let currtime =  Clock::get()?.unix_timestamp;
 
-    (Pubkey::new_from_array(*investor),
+    Ok((Pubkey::new_from_array(*investor),
     Pubkey::new_from_array(*pool_address),
     Pubkey::new_from_array(*address),
     u64::from_le_bytes(*amount),
-    u64::from_le_bytes(*token_count),currtime)
+    u64::from_le_bytes(*token_count),currtime))
+
+
+}
+
+
+// optional [u8;8] after the investor data, the expiry replaced the max
+// slippage [u8;2] of the curve pricing, the amount being the max lamports
+// now, a partial expiry, i.e. the max slippage layout, is rejected
+fn unpack_add_investor_expiry(rest : &[u8]) -> Result<Option<&[u8]>, ProgramError> {
+
+    let extra = rest.len().saturating_sub(INVESTOR_DATA_LEN);
+
+    if extra > 0 && extra < 8 {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    Ok(rest.get(INVESTOR_DATA_LEN..INVESTOR_DATA_LEN + 8))
+}
+
+
+// optional and at the end of the data (after the expiry of AddInvestor), 
+// the allocation [u8;8], the proof length [u8;1] and the proof [u8;32] * length, 
+// when the pool has an allowlist
+fn unpack_allowlist_proof(input : &[u8]) -> Result<AllowlistProof, ProgramError> {
 
+    if input.is_empty() {
 
+        return Ok(AllowlistProof { allocation : 0, proof : Vec::new() });
+    }
+
+    if input.len() < 9 {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let output = array_ref![input, 0, 9];
+    let (allocation, count) = array_refs![output, 8, 1];
+
+    let count = count[0] as usize;
+    let rest = &input[9..];
+
+    // exact, so the max slippage layout of AddInvestor can't pass as a proof
+    if rest.len() != count * HASH_BYTES {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let proof = rest.chunks(HASH_BYTES).take(count).map(|p| *array_ref![p, 0, HASH_BYTES]).collect();
+
+    Ok(AllowlistProof {
+        allocation : u64::from_le_bytes(*allocation),
+        proof,
+    })
 }
 
 
+const FUND_POOL_DATA_LEN : usize = 91;
+
 // [u8;32], [u8;32],[u8;32], [u8;8], [u8;8] ,[u8;8] , [u8;1], [u8;2] 
-fn unpack_fund_pool_data(input : &[u8]) -> (Pubkey, Pubkey,  u64, u64, u64,  bool, u16){
+// manager, address, fee_in_lamports, token_count, token_to_lamport_ratio, is_finalized, icon
+type FundPoolData = (Pubkey, Pubkey,  u64, u64, u64,  bool, u16);
+
+fn unpack_fund_pool_data(input : &[u8]) -> Result<FundPoolData, ProgramError>{
+
+    const L : usize = FUND_POOL_DATA_LEN ;//123; 
+
+    if input.len() < L {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
 
-    const L : usize = 91 ;//123; 
     let output = array_ref![input, 0, L];
     let (manager,address, lamports,token_count,token_to_lamport_ratio, is_finalized,icon) = 
     array_refs![output, PUBKEY_BYTES, PUBKEY_BYTES, 8,8,8, 1, 2 ];
 
-    (  Pubkey::new_from_array(*manager),
+    Ok((  Pubkey::new_from_array(*manager),
     Pubkey::new_from_array(*address),
     u64::from_le_bytes(*lamports),
     u64::from_le_bytes(*token_count),
     u64::from_le_bytes(*token_to_lamport_ratio),
-    unpack_bool(is_finalized).unwrap(),
-    u16::from_le_bytes(*icon))
+    unpack_bool(is_finalized)?,
+    u16::from_le_bytes(*icon)))
 }
 
 
 
+// [u8;32], [u8;32], [u8;2], [u8;1], [u8;8], [u8;8], [u8;8], [u8;8]
+fn unpack_config_data(input : &[u8]) -> Result<ProgramConfigData, ProgramError> {
+
+    const L : usize = 99;
+
+    if input.len() < L {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let output = array_ref![input, 0, L];
+    let (admin, treasury, protocol_fee_bps, is_paused, min_token_count, max_token_count,
+        min_token_to_lamport_ratio, max_fee_in_lamports) =
+    array_refs![output, PUBKEY_BYTES, PUBKEY_BYTES, 2, 1, 8, 8, 8, 8];
+
+    Ok(ProgramConfigData {
+        admin : Pubkey::new_from_array(*admin),
+        treasury : Pubkey::new_from_array(*treasury),
+        protocol_fee_bps : u16::from_le_bytes(*protocol_fee_bps),
+        is_paused : unpack_bool(is_paused)?,
+        min_token_count : u64::from_le_bytes(*min_token_count),
+        max_token_count : u64::from_le_bytes(*max_token_count),
+        min_token_to_lamport_ratio : u64::from_le_bytes(*min_token_to_lamport_ratio),
+        max_fee_in_lamports : u64::from_le_bytes(*max_fee_in_lamports),
+    })
+}
+
+
+// [u8;1] threshold, [u8;1] count, followed by count * [u8;32]
+fn unpack_manager_signers_data(input : &[u8]) -> Result<(u8, Vec<Pubkey>), ProgramError> {
+
+    let (threshold, rest) = input.split_first().ok_or(PoolError::InvalidInstruction)?;
+    let (count, rest) = rest.split_first().ok_or(PoolError::InvalidInstruction)?;
+
+    let count = *count as usize;
+
+    if rest.len() < count * PUBKEY_BYTES {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let signers = rest.chunks(PUBKEY_BYTES).take(count).map(unpack_pub_key).collect();
+
+    Ok((*threshold, signers))
+}
+
+
+fn unpack_investor_limits_data(input : &[u8]) -> Result<(u64, u64, Vec<WhitelistTier>), ProgramError> {
+
+    if input.len() < 17 {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let output = array_ref![input, 0, 17];
+    let (min_tokens, max_tokens, count) = array_refs![output, 8, 8, 1];
+
+    let count = count[0] as usize;
+    let rest = &input[17..];
+
+    if rest.len() < count * 16 {
+
+        return Err(PoolError::InvalidInstruction.into());
+    }
+
+    let tiers = rest.chunks(16).take(count).map(|t| {
+
+        let t = array_ref![t, 0, 16];
+        let (min_tokens, max_tokens) = array_refs![t, 8, 8];
+
+        WhitelistTier {
+            min_tokens : u64::from_le_bytes(*min_tokens),
+            max_tokens : u64::from_le_bytes(*max_tokens),
+        }
+
+    }).collect();
+
+    Ok((u64::from_le_bytes(*min_tokens), u64::from_le_bytes(*max_tokens), tiers))
+}
+
+
 fn unpack_pub_key(array : &[u8]) -> Pubkey{
 
     let mut a : [u8; 32] = [1; 32];
     a.copy_from_slice(array);
     return Pubkey::new_from_array(a);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+
+    #[test]
+    fn test_unpack_short_add_investor() {
+
+        let mut data = vec![MODULE_INVESTOR, ACTION_CREATE];
+        data.extend_from_slice(&[0; INVESTOR_DATA_LEN - 1]);
+
+        assert_eq!(PoolInstruction::unpack(&data).err(), Some(PoolError::InvalidInstruction.into()));
+        assert_eq!(PoolInstruction::unpack(&[MODULE_INVESTOR, ACTION_CREATE]).err(), 
+            Some(PoolError::InvalidInstruction.into()));
+    }
+}
@@ -6,22 +6,32 @@ use {
         account_info::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
         msg,
-        pubkey::Pubkey,
+        pubkey::{Pubkey, PUBKEY_BYTES},
         program_error::ProgramError,
+        bpf_loader_upgradeable,
         program_pack::{Pack},
+        program_option::COption,
         system_instruction,
         program::{invoke,invoke_signed},
+        rent::Rent,
+        clock::Clock,
+        sysvar::Sysvar,
        
         // system_instruction,
        // instruction::{AccountMeta},
        // system_program,
     },
     
-    crate::instruction::PoolInstruction, 
-    crate::state::{FundPool,Market, UserPool, Investor},
+    crate::instruction::{PoolInstruction, ProgramConfigData, AllowlistProof}, 
+    crate::state::{FundPool,Market, UserPool, Investor, ProgramConfig, MintMode,
+        VestingAccount, VestingSchedule, Order, OrderSide, Investment, WhitelistTier, WhitelistEntry,
+        Attestation, PricingMode, PriceTier, Bid, Referral, PriceFeed, BID_SEED, REFERRAL_SEED, ORDER_ESCROW_SEED, INVESTMENT_SEED, WHITELIST_SEED, ATTESTATION_SEED,
+        PROGRAM_CONFIG_SEED, MAX_BASIS_POINTS, MINT_SEED, VAULT_SEED, VESTING_SEED, VESTING_VAULT_SEED},
     crate::{error::PoolError},
+    arrayref::{array_ref, array_refs},
     //spl_token::instruction::initialize_account;
-    spl_token::instruction::{/*initialize_mint,*/mint_to},
+    spl_token::instruction::{initialize_mint, initialize_account2, mint_to},
+    spl_associated_token_account::{get_associated_token_address, create_associated_token_account},
   
     //spl_token ::{ initialize_mint }
 
@@ -46,11 +56,196 @@ pub fn process_instruction(program_id: &Pubkey,accounts: &[AccountInfo], _instru
             update_fund_pool(manager, address, fee_in_lamports, token_count, token_to_lamport_ratio,  is_finalized, icon, program_id, accounts) 
         },
 
+        PoolInstruction::CreateFundPoolWithMint{manager, address, fee_in_lamports, token_count, token_to_lamport_ratio, is_finalized, icon, decimals, mint_mode, is_freezable} => {
+
+            create_fund_pool_with_mint(manager, address, fee_in_lamports, token_count,  token_to_lamport_ratio,  is_finalized, icon, decimals, mint_mode, is_freezable, program_id, accounts)
+        },
+
         PoolInstruction::DeleteFundPool => {
 
             delete_fund_pool(program_id, accounts)
 
         },
+
+        PoolInstruction::WindDownFundPool {signer_count} => {
+
+            wind_down_fund_pool(signer_count, program_id, accounts)
+
+        },
+
+        PoolInstruction::ProposeManager {new_manager} => {
+
+            propose_manager(new_manager, program_id, accounts)
+
+        },
+
+        PoolInstruction::AcceptManager => {
+
+            accept_manager(program_id, accounts)
+
+        },
+
+        PoolInstruction::SetManagerSigners {threshold, signers} => {
+
+            set_manager_signers(threshold, signers, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetPoolPaused {is_paused} => {
+
+            set_pool_paused(is_paused, program_id, accounts)
+
+        },
+
+        PoolInstruction::FinalizeFundPool => {
+
+            finalize_fund_pool(program_id, accounts)
+
+        },
+
+        PoolInstruction::CloseRaise => {
+
+            close_raise(program_id, accounts)
+
+        },
+
+        PoolInstruction::SetVesting {schedule, start, cliff, duration} => {
+
+            set_vesting(schedule, start, cliff, duration, program_id, accounts)
+
+        },
+
+        PoolInstruction::ClaimVested => {
+
+            claim_vested(program_id, accounts)
+
+        },
+
+        PoolInstruction::SetInvestorLimits {min_tokens, max_tokens, tiers} => {
+
+            set_investor_limits(min_tokens, max_tokens, tiers, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetWhitelistTier {investor, tier} => {
+
+            set_whitelist_tier(investor, tier, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetPricing {mode, curve_param} => {
+
+            set_pricing(mode, curve_param, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetPriceTiers {tiers} => {
+
+            set_price_tiers(tiers, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetAuction {start_price, floor_price, start, end} => {
+
+            set_auction(start_price, floor_price, start, end, program_id, accounts)
+
+        },
+
+        PoolInstruction::SettleAuction => {
+
+            settle_auction(program_id, accounts)
+
+        },
+
+        PoolInstruction::MigrateFundPool => {
+
+            migrate_fund_pool(program_id, accounts)
+
+        },
+
+        PoolInstruction::MigrateInvestor => {
+
+            migrate_investor(program_id, accounts)
+
+        },
+
+        PoolInstruction::PlaceBid {token_count, allowlist} => {
+
+            place_bid(token_count, allowlist, program_id, accounts)
+
+        },
+
+        PoolInstruction::ClaimBid => {
+
+            claim_bid(program_id, accounts)
+
+        },
+
+        PoolInstruction::SetOraclePricing {oracle, oracle_program, usd_price, max_age, max_conf_bps} => {
+
+            set_oracle_pricing(oracle, oracle_program, usd_price, max_age, max_conf_bps, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetReferralBps {referral_bps} => {
+
+            set_referral_bps(referral_bps, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetAllowlistRoot {root} => {
+
+            set_allowlist_root(root, program_id, accounts)
+
+        },
+
+        PoolInstruction::SetRequiredVerifier {verifier} => {
+
+            set_required_verifier(verifier, program_id, accounts)
+
+        },
+
+        PoolInstruction::IssueAttestation {wallet, jurisdiction, expiry} => {
+
+            issue_attestation(wallet, jurisdiction, expiry, program_id, accounts)
+
+        },
+
+        PoolInstruction::RevokeAttestation => {
+
+            revoke_attestation(program_id, accounts)
+
+        },
+
+        PoolInstruction::SetHolderFrozen {is_frozen} => {
+
+            set_holder_frozen(is_frozen, program_id, accounts)
+
+        },
+
+        PoolInstruction::PlaceOrder {side, token_count, price, allowlist} => {
+
+            place_order(side, token_count, price, allowlist, program_id, accounts)
+
+        },
+
+        PoolInstruction::CancelOrder => {
+
+            cancel_order(program_id, accounts)
+
+        },
+
+        PoolInstruction::FillOrder {token_count, allowlist} => {
+
+            fill_order(token_count, allowlist, program_id, accounts)
+
+        },
+
+        PoolInstruction::TransferPosition {token_count, allowlist} => {
+
+            transfer_position(token_count, allowlist, program_id, accounts)
+
+        },
        
         PoolInstruction::AddInvestor{
             investor, 
@@ -59,10 +254,12 @@ pub fn process_instruction(program_id: &Pubkey,accounts: &[AccountInfo], _instru
             amount, 
             token_count,
             date, 
+            expiry,
+            allowlist,
       
         } => {
             add_investor(investor, pool_address, address, amount, 
-                 token_count, date, program_id, accounts)
+                 token_count, date, expiry, allowlist, program_id, accounts)
 
         },
 
@@ -84,6 +281,18 @@ pub fn process_instruction(program_id: &Pubkey,accounts: &[AccountInfo], _instru
 
         },
 
+        PoolInstruction::InitProgramConfig {config} => {
+
+            init_program_config(config, program_id, accounts)
+
+        },
+
+        PoolInstruction::UpdateProgramConfig {config} => {
+
+            update_program_config(config, program_id, accounts)
+
+        },
+
        
     }
 
@@ -224,6 +433,198 @@ fn create_market(  creator : Pubkey, program_id: &Pubkey,accounts: &[AccountInfo
    
 }
 
+fn apply_program_config_data(config : &mut ProgramConfig, data : ProgramConfigData) -> ProgramResult {
+
+    if data.protocol_fee_bps > MAX_BASIS_POINTS {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    config.admin = data.admin;
+    config.treasury = data.treasury;
+    config.protocol_fee_bps = data.protocol_fee_bps;
+    config.is_paused = data.is_paused;
+    config.min_token_count = data.min_token_count;
+    config.max_token_count = data.max_token_count;
+    config.min_token_to_lamport_ratio = data.min_token_to_lamport_ratio;
+    config.max_fee_in_lamports = data.max_fee_in_lamports;
+
+    Ok(())
+}
+
+
+/*
+Creates the singleton config PDA, only the program's upgrade
+authority, recorded in its ProgramData account, can create it
+*/
+fn init_program_config(data : ProgramConfigData, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let program_data_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if program_upgrade_authority(program_id, program_data_account)? != Some(*signer_account.key) {
+
+        return Err(ProgramError::from(PoolError::InvalidAdminAccount));
+    }
+
+    let (config_pda, bump_seed) = Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], program_id);
+
+    if *config_account.key != config_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidConfigAccount));
+    }
+
+    if config_account.data_len() > 0 {
+
+        return Err(ProgramError::from(PoolError::ObjectAlreadyCreated));
+    }
+
+    let rent = Rent::get()?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            config_account.key,
+            rent.minimum_balance(ProgramConfig::LEN),
+            ProgramConfig::LEN as u64,
+            program_id,
+        ),
+        &[
+            signer_account.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[PROGRAM_CONFIG_SEED, &[bump_seed]]],
+    )?;
+
+    let mut config = ProgramConfig::new();
+    config.is_initialized = true;
+    apply_program_config_data(&mut config, data)?;
+
+    ProgramConfig::pack(config, &mut config_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The upgrade authority in the ProgramData account of the program, 
+serialized as the u32 tag of the ProgramData state, 
+the slot and an Option<Pubkey>
+*/
+fn program_upgrade_authority(program_id : &Pubkey, program_data_account : &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+
+    const PROGRAM_DATA_TAG : u32 = 3;
+
+    let (program_data, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    if *program_data_account.key != program_data || *program_data_account.owner != bpf_loader_upgradeable::id() {
+
+        return Err(ProgramError::from(PoolError::InvalidAdminAccount));
+    }
+
+    let data = program_data_account.data.borrow();
+
+    if data.len() < 4 + 8 + 1 + PUBKEY_BYTES {
+
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let input = array_ref![data, 0, 4 + 8 + 1 + PUBKEY_BYTES];
+    let (tag, _slot, has_authority, authority) = array_refs![input, 4, 8, 1, PUBKEY_BYTES];
+
+    if u32::from_le_bytes(*tag) != PROGRAM_DATA_TAG {
+
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(if has_authority[0] == 1 { Some(Pubkey::new_from_array(*authority)) } else { None })
+}
+
+
+fn update_program_config(data : ProgramConfigData, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+
+    let mut config = load_program_config(program_id, config_account)?;
+
+    if !config.is_initialized {
+
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    check_program_admin(&config, signer_account)?;
+
+    apply_program_config_data(&mut config, data)?;
+
+    ProgramConfig::pack(config, &mut config_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+Returns the default (uninitialized) config when the
+config PDA hasn't been created, so the program works without one
+*/
+/*
+The config account comes last, after the optional accounts, so the 
+clients' account lists from before the program config keep their positions
+*/
+fn split_config_account<'a, 'b>(accounts : &'a [AccountInfo<'b>]) -> Result<(&'a AccountInfo<'b>, &'a [AccountInfo<'b>]), ProgramError> {
+
+    accounts.split_last().ok_or(ProgramError::NotEnoughAccountKeys)
+}
+
+
+fn load_program_config(program_id : &Pubkey, config_account : &AccountInfo) -> Result<ProgramConfig, ProgramError> {
+
+    let (config_pda, _) = Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], program_id);
+
+    if *config_account.key != config_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidConfigAccount));
+    }
+
+    if config_account.data_len() == 0 {
+
+        return Ok(ProgramConfig::new());
+    }
+
+    is_account_program_owner(program_id, config_account)?;
+
+    ProgramConfig::unpack_unchecked(&config_account.data.borrow())
+}
+
+
+fn check_program_admin(config : &ProgramConfig, signer_account : &AccountInfo) -> ProgramResult {
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !config.is_initialized || *signer_account.key != config.admin {
+
+        return Err(ProgramError::from(PoolError::InvalidAdminAccount));
+    }
+
+    Ok(())
+}
+
+
 fn fund_pool_exists(fund_pool_account : &AccountInfo) -> Result<bool, PoolError> {
 
     let stored_fund_pool = FundPool::unpack_unchecked(&fund_pool_account.data.borrow());
@@ -252,15 +653,15 @@ fn fund_pool_exists(fund_pool_account : &AccountInfo) -> Result<bool, PoolError>
 }
 
 
-const TOKEN_MULTIPLIER : u64 = 1000000000;
-
-
 fn create_fund_pool(  manager : Pubkey,
     address : Pubkey, 
     fee_in_lamports : u64,token_count : u64, 
     token_to_lamport_ratio : u64, 
     is_finalized : bool,
     icon : u16, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
     let account_info_iter = &mut accounts.iter();
 
     let fund_pool_account = next_account_info(account_info_iter)?;
@@ -281,21 +682,23 @@ fn create_fund_pool(  manager : Pubkey,
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    let config = load_program_config(program_id, config_account)?;
+
+    if config.is_paused {
+
+        return Err(ProgramError::from(PoolError::ProgramPaused));
+    }
+
+    config.validate_fund_pool(token_count, token_to_lamport_ratio, fee_in_lamports)?;
+
 
     if is_account_program_owner(program_id, fund_pool_account).unwrap() {
 
        
         if !fund_pool_exists(fund_pool_account).unwrap() {
         
-            let mut w = FundPool::new(true);
-            w.is_finalized = is_finalized;
-            w.token_count = token_count;
-            w.rm_token_count = token_count;
-            w.token_to_lamport_ratio = token_to_lamport_ratio; 
-            w.fee_in_lamports = fee_in_lamports;
-            w.manager = manager;
-            w.icon = icon ; 
-            w.address = address;
+            let mut w = new_fund_pool(manager, address, fee_in_lamports, token_count, 
+                token_to_lamport_ratio, is_finalized, icon);
            
             // generate a PDA here 
             // for later use of holding the lamports 
@@ -304,12 +707,16 @@ fn create_fund_pool(  manager : Pubkey,
             w.pool_pda = pool_pda;
            
            
-            if *token_account.owner != spl_token::id() {
+            if *token_account.owner != spl_token::id() || *token_mint.owner != spl_token::id() {
          
                 return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
             }
 
-            let token_to_mint = token_count * TOKEN_MULTIPLIER;
+            // the token amounts are scaled by the mint's own decimals
+            let mint = spl_token::state::Mint::unpack(&token_mint.data.borrow())?;
+            w.decimals = mint.decimals;
+
+            let token_to_mint = w.token_amount(token_count).ok_or(PoolError::InvalidPoolParameters)?;
 
             let ix = mint_to(
                 token_program.key,
@@ -363,8 +770,11 @@ fn create_fund_pool(  manager : Pubkey,
             
             w.token_pda = pda ;
     
-            // may need to look into how 
-            // to disable further minting when it's marked finalized
+            // no further minting once it's marked finalized
+            if is_finalized {
+
+                revoke_mint_authority(token_program, token_mint, signer_account, &[])?;
+            }
         
     
             w.token_account = *token_account.key; 
@@ -373,106 +783,3346 @@ fn create_fund_pool(  manager : Pubkey,
             FundPool::pack(w, &mut fund_pool_account.data.borrow_mut())?;
 
 
-            if user_pool_account.owner == program_id  {
+            register_new_fund_pool(address, manager, is_finalized, user_pool_account, market_account, program_id);
+          
+        }
+    
+    }
+    Ok(())
+}
+
+
+fn new_fund_pool(manager : Pubkey,
+    address : Pubkey, 
+    fee_in_lamports : u64,token_count : u64, 
+    token_to_lamport_ratio : u64, 
+    is_finalized : bool,
+    icon : u16) -> FundPool {
+
+    let mut w = FundPool::new(true);
+    w.is_finalized = is_finalized;
+    w.token_count = token_count;
+    w.rm_token_count = token_count;
+    w.token_to_lamport_ratio = token_to_lamport_ratio; 
+    w.fee_in_lamports = fee_in_lamports;
+    w.manager = manager;
+    w.icon = icon ; 
+    w.address = address;
+    w
+}
+
+
+fn register_new_fund_pool(address : Pubkey, manager : Pubkey, is_finalized : bool,
+    user_pool_account : &AccountInfo, market_account : &AccountInfo, program_id : &Pubkey) {
+
+    if user_pool_account.owner == program_id  {
+
+        register_address_to_user_pool(address, manager, user_pool_account)
+    }
+
+   
+    if market_account.owner == program_id && is_finalized  {
+
+        register_address_to_market(address, market_account)
+    }
+}
+
+
+/*
+The mint is created at the PDA derived from MINT_SEED and the fund pool,
+the token vault at the PDA derived from VAULT_SEED and the fund pool.
+Both the mint authority and the vault owner are the token_pda, derived 
+from the vault the same way as the client created token account.
+In the Lazy mode nothing is minted into the vault, add_investor mints
+to the investors instead
+*/
+fn create_fund_pool_with_mint(  manager : Pubkey,
+    address : Pubkey, 
+    fee_in_lamports : u64,token_count : u64, 
+    token_to_lamport_ratio : u64, 
+    is_finalized : bool,
+    icon : u16, decimals : u8, mint_mode : MintMode, is_freezable : bool, 
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let user_pool_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?; 
+    let token_pda_account = next_account_info(account_info_iter)?; 
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = load_program_config(program_id, config_account)?;
+
+    if config.is_paused {
+
+        return Err(ProgramError::from(PoolError::ProgramPaused));
+    }
+
+    config.validate_fund_pool(token_count, token_to_lamport_ratio, fee_in_lamports)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    fund_pool_exists(fund_pool_account)?;
+
+    if *token_program.key != spl_token::id() {
+
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut w = new_fund_pool(manager, address, fee_in_lamports, token_count, 
+        token_to_lamport_ratio, is_finalized, icon);
+
+    let pool_addr = &[fund_pool_account.key.as_ref()];
+    let (pool_pda, _bump_seed) = Pubkey::find_program_address(pool_addr, program_id);
+    w.pool_pda = pool_pda;
+    w.decimals = decimals;
+    w.mint_mode = mint_mode;
+    w.is_freezable = is_freezable;
+
+    let token_to_mint = w.token_amount(token_count).ok_or(PoolError::InvalidPoolParameters)?;
+
+    let mint_seeds = &[MINT_SEED, fund_pool_account.key.as_ref()];
+    let (mint_pda, mint_bump) = Pubkey::find_program_address(mint_seeds, program_id);
+
+    let vault_seeds = &[VAULT_SEED, fund_pool_account.key.as_ref()];
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(vault_seeds, program_id);
+
+    if *token_mint.key != mint_pda || *token_account.key != vault_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let addr = &[token_account.key.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+    if *token_pda_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let rent = Rent::from_account_info(rent_account)?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            token_mint.key,
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            token_program.key,
+        ),
+        &[
+            signer_account.clone(),
+            token_mint.clone(),
+            system_program.clone(),
+        ],
+        &[&[MINT_SEED, fund_pool_account.key.as_ref(), &[mint_bump]]],
+    )?;
+
+    invoke(
+        &initialize_mint(token_program.key, token_mint.key, &pda, 
+            if is_freezable { Some(&pda) } else { None }, decimals)?,
+        &[
+            token_mint.clone(),
+            rent_account.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            token_account.key,
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            token_program.key,
+        ),
+        &[
+            signer_account.clone(),
+            token_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[VAULT_SEED, fund_pool_account.key.as_ref(), &[vault_bump]]],
+    )?;
+
+    invoke(
+        &initialize_account2(token_program.key, token_account.key, token_mint.key, &pda)?,
+        &[
+            token_account.clone(),
+            token_mint.clone(),
+            rent_account.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let pda_signer_seeds : &[&[u8]] = &[addr[0], &[bump_seed]];
+
+    if mint_mode == MintMode::Program {
+
+        invoke_signed(
+            &mint_to(token_program.key, token_mint.key, token_account.key, &pda, &[], token_to_mint)?,
+            &[
+                token_mint.clone(),
+                token_account.clone(),
+                token_pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[pda_signer_seeds],
+        )?;
+    }
+
+    // a lazy pool keeps the mint authority, the program only 
+    // mints what's sold, capped by the rm_token_count
+    if is_finalized && mint_mode == MintMode::Program {
+
+        revoke_mint_authority(token_program, token_mint, token_pda_account, &[pda_signer_seeds])?;
+    }
+
+    w.token_pda = pda;
+    w.token_account = *token_account.key; 
+    w.token_mint = *token_mint.key;
+
+    FundPool::pack(w, &mut fund_pool_account.data.borrow_mut())?;
+
+    register_new_fund_pool(address, manager, is_finalized, user_pool_account, market_account, program_id);
+
+    Ok(())
+}
+
+
+/*
+Maybe needed in the future, currently isn't called by client
+*/
+fn update_fund_pool(manager : Pubkey,
+    address : Pubkey,fee_in_lamports : u64,token_count : u64, 
+    token_to_lamport_ratio : u64, 
+    is_finalized : bool,
+    icon : u16, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let account = next_account_info(account_info_iter)?;
+
+    if is_account_program_owner(program_id, account).unwrap() {
+
+        let mut w = FundPool::unpack_unchecked(&account.data.borrow())?;
+
+        check_pool_manager(&w, accounts)?;
+
+        // the protocol's limits hold after the creation too
+        let config = load_program_config(program_id, config_account)?;
+
+        config.validate_fund_pool(token_count, token_to_lamport_ratio, fee_in_lamports)?;
+
+        if is_finalized != w.is_finalized {
+
+            msg!("Use FinalizeFundPool to finalize, a finalized pool can't be reverted!!");
+            return Err(ProgramError::from(PoolError::InvalidAction));
+        }
+
+        // the supply and the price are locked once finalized or closed
+        if (w.is_finalized || w.is_raise_closed) && (token_count != w.token_count || 
+            token_to_lamport_ratio != w.token_to_lamport_ratio) {
+
+            return Err(ProgramError::from(PoolError::PoolFinalized));
+        }
+
+        // the sold count and so the price follow the supply, locked once 
+        // any token is sold or the pool is priced by another mode
+        let supply_changed = token_count != w.token_count || token_to_lamport_ratio != w.token_to_lamport_ratio;
+
+        if supply_changed && (w.sold_count() > 0 || w.investor_count() > 0 || w.pricing_mode != PricingMode::Fixed) {
+
+            return Err(ProgramError::from(PoolError::PricingLocked));
+        }
+
+        if w.manager == manager && w.address == address {
+            // nothing is sold yet when the supply changes
+            if supply_changed {
+
+                w.rm_token_count = token_count;
+            }
+
+            w.token_count = token_count;
+            w.token_to_lamport_ratio = token_to_lamport_ratio; 
+            w.fee_in_lamports = fee_in_lamports;
+            w.icon = icon;
+            FundPool::pack(w, &mut account.data.borrow_mut())?;
+        }
+        else {
+
+            msg!("No update, different manager, can't change manager!!");
+        }
+       
+    }
+    Ok(())
+}
+
+/*
+Maybe needed in the future
+*/
+fn delete_fund_pool(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+
+    msg!("Deleting fund pool...");
+    
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let account = next_account_info(account_info_iter)?;
+    let user_pool_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let pool_pda_account = next_account_info(account_info_iter)?;
+    let manager_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+
+    // check for signer
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+
+    if is_account_program_owner(program_id, account).unwrap() {
+
+
+        let fund_pool = FundPool::unpack_unchecked(&account.data.borrow())?;
+
+        check_pool_manager_or_admin(&fund_pool, program_id, config_account, accounts)?;
+
+        let pool_addr = &[account.key.as_ref()];
+        let (pool_pda, bump_seed) = Pubkey::find_program_address(pool_addr, program_id);
+
+        if *pool_pda_account.key != fund_pool.pool_pda || pool_pda != fund_pool.pool_pda {
+
+            return Err(ProgramError::from(PoolError::UnmatchedPoolAddress));
+        }
+
+        // refuse to wipe a pool that still owes its investors or its 
+        // bidders, the manager must wind it down first
+        if fund_pool.investor_count() > 0 || fund_pool.bid_lamports > 0 {
+
+            return Err(ProgramError::from(PoolError::PoolNotEmpty));
+        }
+
+        if *manager_account.key != fund_pool.manager {
+
+            return Err(ProgramError::from(PoolError::InvalidManagerAccount));
+        }
+
+        // nothing is owed anymore, whatever is left goes to the manager
+        let left = pool_pda_account.lamports();
+
+        if left > 0 {
+
+            invoke_signed(
+                &system_instruction::transfer(&pool_pda, manager_account.key, left),
+                &[
+                    pool_pda_account.clone(),
+                    manager_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[pool_addr[0], &[bump_seed]]],
+            )?;
+        }
+    
+        let zeros = &vec![0; account.data_len()];
+
+        account.data.borrow_mut()[0..zeros.len()].copy_from_slice(zeros);
+
+       
+        if user_pool_account.owner == program_id  {
+
+            remove_address_from_user_pool(fund_pool.address, fund_pool.manager, user_pool_account)
+        }
+        
+       
+        if market_account.owner == program_id  {
+
+            remove_address_from_market(fund_pool.address, market_account)
+        }
+      
+
+    }
+    Ok(())
+}
+
+
+/*
+The manager signers can be anywhere in the accounts, when the pool
+has a manager set, at least threshold distinct members must have signed,
+otherwise the single manager must have signed
+*/
+fn check_pool_manager(fund_pool : &FundPool, accounts : &[AccountInfo]) -> ProgramResult {
+
+    if fund_pool.is_multisig() {
+
+        let members = fund_pool.manager_signers();
+        let mut signed : Vec<Pubkey> = Vec::with_capacity(members.len());
+
+        for a in accounts {
+
+            if a.is_signer && members.contains(a.key) && !signed.contains(a.key) {
+
+                signed.push(*a.key);
+            }
+        }
+
+        if signed.len() < fund_pool.manager_threshold as usize {
+
+            return Err(ProgramError::from(PoolError::NotEnoughManagerSigners));
+        }
+
+        return Ok(());
+    }
+
+    if !accounts.iter().any(|a| a.is_signer && *a.key == fund_pool.manager) {
+
+        return Err(ProgramError::from(PoolError::InvalidManagerAccount));
+    }
+
+    Ok(())
+}
+
+
+/*
+The program admin can act on any pool, e.g. to force a wind down
+*/
+fn check_pool_manager_or_admin(fund_pool : &FundPool, program_id : &Pubkey, 
+    config_account : &AccountInfo, accounts : &[AccountInfo]) -> ProgramResult {
+
+    let config = load_program_config(program_id, config_account)?;
+
+    if config.is_initialized && accounts.iter().any(|a| a.is_signer && *a.key == config.admin) {
+
+        return Ok(());
+    }
+
+    check_pool_manager(fund_pool, accounts)
+}
+
+
+/*
+Either the manager or the program admin can pause a pool,
+refunds by winding down are still allowed on a paused pool
+*/
+fn set_pool_paused(is_paused : bool, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager_or_admin(&fp, program_id, config_account, accounts)?;
+
+    fp.is_paused = is_paused;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_holder_frozen(is_frozen : bool, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let holder_token_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if !fp.is_freezable {
+
+        return Err(ProgramError::from(PoolError::PoolNotFreezable));
+    }
+
+    set_token_account_frozen(&fp, is_frozen, holder_token_account, token_mint, 
+        token_pda_account, token_program, program_id)
+}
+
+
+/*
+Freezes or thaws the token account with the token_pda as 
+the mint's freeze authority, does nothing if already in that state
+*/
+fn set_token_account_frozen<'a>(fp : &FundPool, is_frozen : bool, token_account : &AccountInfo<'a>,
+    token_mint : &AccountInfo<'a>, token_pda_account : &AccountInfo<'a>, token_program : &AccountInfo<'a>,
+    program_id : &Pubkey) -> ProgramResult {
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let acc = spl_token::state::Account::unpack(&token_account.data.borrow())?;
+
+    if acc.mint != fp.token_mint {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    if acc.is_frozen() == is_frozen {
+
+        return Ok(());
+    }
+
+    let ix = if is_frozen {
+        spl_token::instruction::freeze_account(token_program.key, token_account.key, 
+            token_mint.key, &fp.token_pda, &[])?
+    }
+    else {
+        spl_token::instruction::thaw_account(token_program.key, token_account.key, 
+            token_mint.key, &fp.token_pda, &[])?
+    };
+
+    let addr = &[fp.token_account.as_ref()];
+    let (_, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+    invoke_signed(&ix,
+        &[
+            token_account.clone(),
+            token_mint.clone(),
+            token_pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[addr[0], &[bump_seed]]],
+    )
+}
+
+
+/*
+The pool's gates on the wallet holding the position's token count, 
+its whitelist tier limits, its attestation and its allowlist leaf. 
+The whitelist entry and the attestation follow in the accounts only 
+when the pool has tiers or requires a verifier, in this order
+*/
+fn check_investor_gates<'a, 'b>(fp : &FundPool, fund_pool_account : &AccountInfo, wallet : &Pubkey, 
+    token_count : u64, allowlist : &AllowlistProof, account_info_iter : &mut std::slice::Iter<'a, AccountInfo<'b>>, 
+    program_id : &Pubkey) -> ProgramResult {
+
+    let tier = if fp.has_tiers() {
+
+        let whitelist_account = next_account_info(account_info_iter)?;
+
+        load_whitelist_tier(fund_pool_account, wallet, whitelist_account, program_id)?
+    }
+    else {
+
+        0
+    };
+
+    fp.check_investor_token_count(token_count, tier)?;
+
+    if fp.required_verifier != Pubkey::default() {
+
+        let attestation_account = next_account_info(account_info_iter)?;
+
+        check_attestation(&fp.required_verifier, wallet, attestation_account, program_id)?;
+    }
+
+    if fp.has_allowlist() {
+
+        if !fp.is_allowlisted(wallet, allowlist.allocation, &allowlist.proof) {
+
+            return Err(ProgramError::from(PoolError::NotAllowlisted));
+        }
+
+        if allowlist.allocation > 0 && token_count > allowlist.allocation {
+
+            return Err(ProgramError::from(PoolError::AboveAllocation));
+        }
+    }
+
+    Ok(())
+}
+
+
+/*
+Loads the investor record of the wallet in the fund pool, 
+an empty record has the default investor
+*/
+fn load_investor_record(fp : &FundPool, wallet : &Pubkey, investor_account : &AccountInfo, 
+    program_id : &Pubkey) -> Result<Investor, ProgramError> {
+
+    is_account_program_owner(program_id, investor_account)?;
+
+    let i = Investor::unpack_unchecked(&investor_account.data.borrow())?;
+
+    if i.investor != Pubkey::default() && 
+    (i.investor != *wallet || i.pool_address != fp.address || i.address != *investor_account.key) {
+
+        return Err(ProgramError::from(PoolError::UnmatchedInvestorAccountAddress));
+    }
+
+    Ok(i)
+}
+
+
+fn check_holder_token_account(fp : &FundPool, owner : &Pubkey, token_account : &AccountInfo) -> ProgramResult {
+
+    let acc = spl_token::state::Account::unpack(&token_account.data.borrow())?;
+
+    if acc.mint != fp.token_mint || acc.owner != *owner {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    Ok(())
+}
+
+
+/*
+A buy order's gating accounts (see check_investor_gates) 
+follow the system program, a sell order's escrow accounts follow it
+*/
+fn place_order(side : OrderSide, token_count : u64, price : u64, allowlist : AllowlistProof,
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let order_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let investor_pool_account = next_account_info(account_info_iter)?;
+    let owner_token_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, order_account)?;
+
+    let config = load_program_config(program_id, config_account)?;
+
+    let fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_not_paused(&config, &fp)?;
+
+    if token_count == 0 || price == 0 {
+
+        return Err(ProgramError::from(PoolError::InvalidOrderParameters));
+    }
+
+    let lamports = token_count.checked_mul(price).ok_or(PoolError::InvalidOrderParameters)?;
+
+    let mut o = Order::unpack_unchecked(&order_account.data.borrow())?;
+
+    if o.is_initialized {
+
+        return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+    }
+
+    let mut i = load_investor_record(&fp, signer_account.key, investor_account, program_id)?;
+
+    check_holder_token_account(&fp, signer_account.key, owner_token_account)?;
+
+    o.is_initialized = true;
+    o.fund_pool = *fund_pool_account.key;
+    o.owner = *signer_account.key;
+    o.investor_account = *investor_account.key;
+    o.token_account = *owner_token_account.key;
+    o.side = side;
+    o.price = price;
+    o.token_count = token_count;
+    o.date = Clock::get()?.unix_timestamp;
+
+    match side {
+
+        OrderSide::Buy => {
+
+            let position = i.token_count.checked_add(token_count).ok_or(PoolError::InvalidOrderParameters)?;
+
+            check_investor_gates(&fp, fund_pool_account, signer_account.key, position, 
+                &allowlist, account_info_iter, program_id)?;
+
+            // the bought tokens are credited to this record on fills
+            if i.investor == Pubkey::default() {
+
+                i.investor = *signer_account.key;
+                i.pool_address = fp.address;
+                i.address = *investor_account.key;
+                i.token_account = *owner_token_account.key;
+                i.date = o.date;
+
+                Investor::pack(i, &mut investor_account.data.borrow_mut())?;
+
+                register_address_to_user_pool(*investor_account.key, *signer_account.key, investor_pool_account);
+            }
+
+            invoke(
+                &system_instruction::transfer(signer_account.key, order_account.key, lamports),
+                &[
+                    signer_account.clone(),
+                    order_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        },
+
+        OrderSide::Sell => {
+
+            let escrow_account = next_account_info(account_info_iter)?;
+            let token_mint = next_account_info(account_info_iter)?;
+            let token_pda_account = next_account_info(account_info_iter)?;
+            let token_program = next_account_info(account_info_iter)?;
+            let rent_account = next_account_info(account_info_iter)?;
+
+            // a position backs one sell order at a time
+            if i.available_token_count() < token_count {
+
+                return Err(ProgramError::from(PoolError::InsufficientPosition));
+            }
+
+            i.escrowed_token_count += token_count;
+
+            let escrow_seeds = &[ORDER_ESCROW_SEED, order_account.key.as_ref()];
+            let (escrow, escrow_bump) = Pubkey::find_program_address(escrow_seeds, program_id);
+
+            if *escrow_account.key != escrow {
+
+                return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+            }
+
+            let rent = Rent::from_account_info(rent_account)?;
+
+            invoke_signed(
+                &system_instruction::create_account(
+                    signer_account.key,
+                    escrow_account.key,
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    token_program.key,
+                ),
+                &[
+                    signer_account.clone(),
+                    escrow_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[ORDER_ESCROW_SEED, order_account.key.as_ref(), &[escrow_bump]]],
+            )?;
+
+            // the escrow owns itself, so only the program moves the tokens out
+            invoke(
+                &initialize_account2(token_program.key, escrow_account.key, token_mint.key, &escrow)?,
+                &[
+                    escrow_account.clone(),
+                    token_mint.clone(),
+                    rent_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, false, owner_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+
+            invoke(
+                &spl_token::instruction::transfer_checked(
+                    token_program.key,
+                    owner_token_account.key,
+                    token_mint.key,
+                    escrow_account.key,
+                    signer_account.key,
+                    &[],
+                    fp.token_amount(token_count).ok_or(PoolError::InvalidOrderParameters)?,
+                    fp.decimals,
+                )?,
+                &[
+                    owner_token_account.clone(),
+                    token_mint.clone(),
+                    escrow_account.clone(),
+                    signer_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, true, owner_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+
+            o.escrow = escrow;
+
+            Investor::pack(i, &mut investor_account.data.borrow_mut())?;
+        },
+    }
+
+    Order::pack(o, &mut order_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+Cancelling is allowed while paused, the order account is closed
+and its lamports go back to the owner, a sell order releases its 
+escrowed token count in the owner's investor record
+*/
+fn cancel_order(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let order_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, order_account)?;
+
+    let o = Order::unpack(&order_account.data.borrow())?;
+
+    if o.owner != *signer_account.key || o.fund_pool != *fund_pool_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+    }
+
+    if o.side == OrderSide::Sell {
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let token_pda_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let investor_account = next_account_info(account_info_iter)?;
+
+        if *escrow_account.key != o.escrow || *owner_token_account.key != o.token_account ||
+        *investor_account.key != o.investor_account {
+
+            return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+        }
+
+        let fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+        let mut i = load_investor_record(&fp, signer_account.key, investor_account, program_id)?;
+
+        // the record is gone once the pool refunded it
+        if i.investor != Pubkey::default() {
+
+            i.escrowed_token_count = i.escrowed_token_count.saturating_sub(o.token_count);
+
+            Investor::pack(i, &mut investor_account.data.borrow_mut())?;
+        }
+
+        let escrow_seeds = &[ORDER_ESCROW_SEED, order_account.key.as_ref()];
+        let (_, escrow_bump) = Pubkey::find_program_address(escrow_seeds, program_id);
+        let signer_seeds : &[&[u8]] = &[ORDER_ESCROW_SEED, order_account.key.as_ref(), &[escrow_bump]];
+
+        let left = spl_token::state::Account::unpack(&escrow_account.data.borrow())?.amount;
+
+        if left > 0 {
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, false, owner_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+
+            invoke_signed(
+                &spl_token::instruction::transfer_checked(
+                    token_program.key,
+                    escrow_account.key,
+                    token_mint.key,
+                    owner_token_account.key,
+                    escrow_account.key,
+                    &[],
+                    left,
+                    fp.decimals,
+                )?,
+                &[
+                    escrow_account.clone(),
+                    token_mint.clone(),
+                    owner_token_account.clone(),
+                    token_program.clone(),
+                ],
+                &[signer_seeds],
+            )?;
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, true, owner_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+        }
+
+        close_order_escrow(order_account, escrow_account, signer_account, token_program, program_id)?;
+    }
+
+    close_order_account(order_account, signer_account)
+}
+
+
+/*
+Closes the emptied escrow token account of a sell order, 
+its rent goes to the owner
+*/
+fn close_order_escrow<'a>(order_account : &AccountInfo<'a>, escrow_account : &AccountInfo<'a>, 
+    owner_account : &AccountInfo<'a>, token_program : &AccountInfo<'a>, program_id : &Pubkey) -> ProgramResult {
+
+    let escrow_seeds = &[ORDER_ESCROW_SEED, order_account.key.as_ref()];
+    let (_, escrow_bump) = Pubkey::find_program_address(escrow_seeds, program_id);
+
+    invoke_signed(
+        &spl_token::instruction::close_account(
+            token_program.key,
+            escrow_account.key,
+            owner_account.key,
+            escrow_account.key,
+            &[],
+        )?,
+        &[
+            escrow_account.clone(),
+            owner_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[ORDER_ESCROW_SEED, order_account.key.as_ref(), &[escrow_bump]]],
+    )
+}
+
+
+/*
+Clears the order, its lamports, the escrowed lamports
+of a buy order and the rent, go back to the owner
+*/
+fn close_order_account(order_account : &AccountInfo, owner_account : &AccountInfo) -> ProgramResult {
+
+    Order::pack(Order::new(), &mut order_account.data.borrow_mut())?;
+
+    let lamports = order_account.lamports();
+    **order_account.lamports.borrow_mut() = 0;
+    **owner_account.lamports.borrow_mut() = owner_account.lamports()
+    .checked_add(lamports).ok_or(PoolError::AmountsUnmatched)?;
+
+    Ok(())
+}
+
+
+/*
+The taker fills the order at its price, the position moves from the 
+seller's investor record to the buyer's with the part of the amount 
+paid into the pool, so the refunds from the pool_pda still add up.
+A fully filled order is closed to the maker.
+The buyer's gating accounts (see check_investor_gates) come last
+*/
+fn fill_order(token_count : u64, allowlist : AllowlistProof, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let order_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let maker_account = next_account_info(account_info_iter)?;
+    let maker_investor_account = next_account_info(account_info_iter)?;
+    let maker_token_account = next_account_info(account_info_iter)?;
+    let taker_investor_account = next_account_info(account_info_iter)?;
+    let taker_pool_account = next_account_info(account_info_iter)?;
+    let taker_token_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, order_account)?;
+
+    let config = load_program_config(program_id, config_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_not_paused(&config, &fp)?;
+
+    let mut o = Order::unpack(&order_account.data.borrow())?;
+
+    if o.fund_pool != *fund_pool_account.key || o.owner != *maker_account.key ||
+    o.investor_account != *maker_investor_account.key || o.token_account != *maker_token_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+    }
+
+    if token_count == 0 || token_count > o.token_count || o.owner == *signer_account.key ||
+    maker_investor_account.key == taker_investor_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidOrderParameters));
+    }
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    check_holder_token_account(&fp, signer_account.key, taker_token_account)?;
+
+    let mut maker = load_investor_record(&fp, maker_account.key, maker_investor_account, program_id)?;
+    let mut taker = load_investor_record(&fp, signer_account.key, taker_investor_account, program_id)?;
+
+    let lamports = token_count.checked_mul(o.price).ok_or(PoolError::InvalidOrderParameters)?;
+    let token_to_tx = fp.token_amount(token_count).ok_or(PoolError::InvalidOrderParameters)?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, false, taker_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    let mut escrow = None;
+
+    match o.side {
+
+        OrderSide::Sell => {
+
+            let escrow_account = next_account_info(account_info_iter)?;
+
+            escrow = Some(escrow_account);
+
+            if *escrow_account.key != o.escrow {
+
+                return Err(ProgramError::from(PoolError::InvalidOrderAccount));
+            }
+
+            let position = taker.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+            check_investor_gates(&fp, fund_pool_account, signer_account.key, position, 
+                &allowlist, account_info_iter, program_id)?;
+
+            invoke(
+                &system_instruction::transfer(signer_account.key, maker_account.key, lamports),
+                &[
+                    signer_account.clone(),
+                    maker_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+
+            let escrow_seeds = &[ORDER_ESCROW_SEED, order_account.key.as_ref()];
+            let (_, escrow_bump) = Pubkey::find_program_address(escrow_seeds, program_id);
+
+            invoke_signed(
+                &spl_token::instruction::transfer_checked(
+                    token_program.key,
+                    escrow_account.key,
+                    token_mint.key,
+                    taker_token_account.key,
+                    escrow_account.key,
+                    &[],
+                    token_to_tx,
+                    fp.decimals,
+                )?,
+                &[
+                    escrow_account.clone(),
+                    token_mint.clone(),
+                    taker_token_account.clone(),
+                    token_program.clone(),
+                ],
+                &[&[ORDER_ESCROW_SEED, order_account.key.as_ref(), &[escrow_bump]]],
+            )?;
+
+            maker.escrowed_token_count = maker.escrowed_token_count.saturating_sub(token_count);
+
+            move_position_to(&mut fp, &mut maker, &mut taker, token_count, &PositionReceiver {
+                wallet : signer_account.key,
+                investor_account : taker_investor_account,
+                pool_account : taker_pool_account,
+                token_account : taker_token_account,
+            })?;
+        },
+
+        OrderSide::Buy => {
+
+            check_holder_token_account(&fp, maker_account.key, maker_token_account)?;
+
+            let position = maker.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+            check_investor_gates(&fp, fund_pool_account, maker_account.key, position, 
+                &allowlist, account_info_iter, program_id)?;
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, false, maker_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+
+            invoke(
+                &spl_token::instruction::transfer_checked(
+                    token_program.key,
+                    taker_token_account.key,
+                    token_mint.key,
+                    maker_token_account.key,
+                    signer_account.key,
+                    &[],
+                    token_to_tx,
+                    fp.decimals,
+                )?,
+                &[
+                    taker_token_account.clone(),
+                    token_mint.clone(),
+                    maker_token_account.clone(),
+                    signer_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+
+            if fp.is_freezable {
+
+                set_token_account_frozen(&fp, true, maker_token_account, token_mint, 
+                    token_pda_account, token_program, program_id)?;
+            }
+
+            move_position(&mut fp, &mut taker, &mut maker, token_count)?;
+
+            // the escrowed lamports are in the order account owned by the program
+            **order_account.lamports.borrow_mut() = order_account.lamports()
+            .checked_sub(lamports).ok_or(PoolError::AmountsUnmatched)?;
+            **signer_account.lamports.borrow_mut() = signer_account.lamports()
+            .checked_add(lamports).ok_or(PoolError::AmountsUnmatched)?;
+        },
+    }
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, true, taker_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    o.token_count -= token_count;
+
+    if o.token_count == 0 {
+
+        if let Some(escrow_account) = escrow {
+
+            close_order_escrow(order_account, escrow_account, maker_account, token_program, program_id)?;
+        }
+
+        close_order_account(order_account, maker_account)?;
+    }
+    else {
+
+        Order::pack(o, &mut order_account.data.borrow_mut())?;
+    }
+
+    Investor::pack(maker, &mut maker_investor_account.data.borrow_mut())?;
+    Investor::pack(taker, &mut taker_investor_account.data.borrow_mut())?;
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+Moves the token count and its part of the amount from one investor 
+record to the other and updates the pool's investors registry
+*/
+fn move_position(fp : &mut FundPool, from : &mut Investor, to : &mut Investor, token_count : u64) -> ProgramResult {
+
+    if from.investor == Pubkey::default() || token_count > from.available_token_count() {
+
+        return Err(ProgramError::from(PoolError::InsufficientPosition));
+    }
+
+    let amount = from.amount_of(token_count).ok_or(PoolError::InsufficientPosition)?;
+
+    from.token_count -= token_count;
+    from.amount -= amount;
+
+    to.token_count = to.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+    to.amount = to.amount.checked_add(amount).ok_or(PoolError::AmountsUnmatched)?;
+
+    fp.set_investor_token_count(from.investor, from.address, from.token_count)?;
+
+    if to.investor != Pubkey::default() {
+
+        fp.set_investor_token_count(to.investor, to.address, to.token_count)?;
+    }
+
+    Ok(())
+}
+
+
+// the wallet receiving a position with its investor, user pool and token accounts
+#[derive(Clone, Copy)]
+struct PositionReceiver<'a, 'b> {
+
+    wallet : &'a Pubkey,
+
+    investor_account : &'a AccountInfo<'b>,
+
+    pool_account : &'a AccountInfo<'b>,
+
+    token_account : &'a AccountInfo<'b>,
+}
+
+
+/*
+Like move_position, but starts the receiving record when empty
+and registers it to the receiver's user pool
+*/
+fn move_position_to(fp : &mut FundPool, from : &mut Investor, to : &mut Investor, token_count : u64, 
+    receiver : &PositionReceiver) -> ProgramResult {
+
+    let PositionReceiver { wallet, investor_account : to_investor_account, 
+        pool_account : to_pool_account, token_account : to_token_account } = *receiver;
+
+    let is_new = to.investor == Pubkey::default();
+
+    move_position(fp, from, to, token_count)?;
+
+    if is_new {
+
+        to.investor = *wallet;
+        to.pool_address = fp.address;
+        to.address = *to_investor_account.key;
+        to.token_account = *to_token_account.key;
+        to.date = Clock::get()?.unix_timestamp;
+
+        fp.set_investor_token_count(to.investor, to.address, to.token_count)?;
+
+        register_address_to_user_pool(*to_investor_account.key, *wallet, to_pool_account);
+    }
+
+    Ok(())
+}
+
+
+/*
+Moves some or all of the signer's position and its tokens to 
+the recipient, allowed while paused as no new funds come in.
+The recipient's gating accounts (see check_investor_gates) come last
+*/
+fn transfer_position(token_count : u64, allowlist : AllowlistProof, 
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let from_investor_account = next_account_info(account_info_iter)?;
+    let to_investor_account = next_account_info(account_info_iter)?;
+    let to_pool_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let recipient_account = next_account_info(account_info_iter)?;
+    let from_token_account = next_account_info(account_info_iter)?;
+    let to_token_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    if token_count == 0 || signer_account.key == recipient_account.key ||
+    from_investor_account.key == to_investor_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidInstruction));
+    }
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    check_holder_token_account(&fp, signer_account.key, from_token_account)?;
+    check_holder_token_account(&fp, recipient_account.key, to_token_account)?;
+
+    let mut from = load_investor_record(&fp, signer_account.key, from_investor_account, program_id)?;
+    let mut to = load_investor_record(&fp, recipient_account.key, to_investor_account, program_id)?;
+
+    let position = to.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+    check_investor_gates(&fp, fund_pool_account, recipient_account.key, position, 
+        &allowlist, account_info_iter, program_id)?;
+
+    move_position_to(&mut fp, &mut from, &mut to, token_count, &PositionReceiver {
+        wallet : recipient_account.key,
+        investor_account : to_investor_account,
+        pool_account : to_pool_account,
+        token_account : to_token_account,
+    })?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, false, from_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+        set_token_account_frozen(&fp, false, to_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    invoke(
+        &spl_token::instruction::transfer_checked(
+            token_program.key,
+            from_token_account.key,
+            token_mint.key,
+            to_token_account.key,
+            signer_account.key,
+            &[],
+            fp.token_amount(token_count).ok_or(PoolError::AmountsUnmatched)?,
+            fp.decimals,
+        )?,
+        &[
+            from_token_account.clone(),
+            token_mint.clone(),
+            to_token_account.clone(),
+            signer_account.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, true, from_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+        set_token_account_frozen(&fp, true, to_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    Investor::pack(from, &mut from_investor_account.data.borrow_mut())?;
+    Investor::pack(to, &mut to_investor_account.data.borrow_mut())?;
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+Creates the history account of this investment at its PDA 
+and bumps the investor's investment count
+*/
+fn record_investment<'a>(i : &mut Investor, amount : u64, token_count : u64, 
+    fund_pool_account : &AccountInfo<'a>, investment_account : &AccountInfo<'a>, 
+    payer : Payer<'_, 'a>, program_id : &Pubkey) -> ProgramResult {
+
+    let Payer { signer_account, system_program, rent_account } = payer;
+
+    let index = i.investment_count.to_le_bytes();
+
+    let seeds = &[INVESTMENT_SEED, i.address.as_ref(), &index];
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+
+    if *investment_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidInvestmentAccount));
+    }
+
+    let rent = Rent::from_account_info(rent_account)?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            investment_account.key,
+            rent.minimum_balance(Investment::LEN),
+            Investment::LEN as u64,
+            program_id,
+        ),
+        &[
+            signer_account.clone(),
+            investment_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[INVESTMENT_SEED, i.address.as_ref(), &index, &[bump_seed]]],
+    )?;
+
+    let mut iv = Investment::new();
+    iv.is_initialized = true;
+    iv.investor_account = i.address;
+    iv.fund_pool = *fund_pool_account.key;
+    iv.index = i.investment_count;
+    iv.amount = amount;
+    iv.token_count = token_count;
+    iv.date = Clock::get()?.unix_timestamp;
+
+    Investment::pack(iv, &mut investment_account.data.borrow_mut())?;
+
+    i.investment_count += 1;
+
+    Ok(())
+}
+
+
+fn check_not_paused(config : &ProgramConfig, fund_pool : &FundPool) -> ProgramResult {
+
+    if config.is_paused {
+
+        return Err(ProgramError::from(PoolError::ProgramPaused));
+    }
+
+    if fund_pool.is_paused {
+
+        return Err(ProgramError::from(PoolError::PoolPaused));
+    }
+
+    Ok(())
+}
+
+
+/*
+Marks the pool finalized, revokes the mint authority of the token mint,
+so the supply is locked, and lists the pool in the market
+*/
+fn finalize_fund_pool(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.is_finalized {
+
+        return Err(ProgramError::from(PoolError::PoolFinalized));
+    }
+
+    if *token_mint.key != fp.token_mint {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let mint = spl_token::state::Mint::unpack(&token_mint.data.borrow())?;
+
+    // a lazy pool keeps the mint authority to mint to the investors,
+    // the supply is still capped by the rm_token_count
+    if fp.mint_mode == MintMode::Lazy {
+
+        msg!("Lazy minted pool, the mint authority stays with the token_pda");
+    }
+    else if let COption::Some(mint_authority) = mint.mint_authority {
+
+        if fp.mint_mode == MintMode::Program && mint_authority == fp.token_pda {
+
+            // the program holds the mint authority, sign with the token_pda seeds
+            let authority_account = accounts.iter()
+            .find(|a| *a.key == mint_authority)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+            let (_, bump_seed) = Pubkey::find_program_address(&[fp.token_account.as_ref()], program_id);
+
+            revoke_mint_authority(token_program, token_mint, authority_account, 
+                &[&[fp.token_account.as_ref(), &[bump_seed]]])?;
+        }
+        else {
+
+            // the current mint authority must be one of the signers
+            let authority_account = accounts.iter()
+            .find(|a| *a.key == mint_authority && a.is_signer)
+            .ok_or(ProgramError::MissingRequiredSignature)?;
+
+            revoke_mint_authority(token_program, token_mint, authority_account, &[])?;
+        }
+    }
+
+    fp.is_finalized = true;
+
+    let address = fp.address;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    if market_account.owner == program_id  {
+
+        register_address_to_market(address, market_account)
+    }
+
+    Ok(())
+}
+
+
+/*
+Burns whatever is left in the pool's token vault, signed by the token_pda,
+so the unsold tokens no longer exist, and no more investment is taken
+*/
+fn close_raise(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::RaiseClosed));
+    }
+
+    if *token_account.key != fp.token_account || *token_mint.key != fp.token_mint || 
+    *token_account.owner != spl_token::id() {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let addr = &[token_account.key.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+    if *token_pda_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    // the tokens owed to the auction's bidders stay in the vault until claimed
+    let vault = spl_token::state::Account::unpack(&token_account.data.borrow())?;
+    let unsold = fp.token_amount(fp.rm_token_count).ok_or(PoolError::AmountsUnmatched)?.min(vault.amount);
+
+    if unsold > 0 {
+
+        let burn_ix = spl_token::instruction::burn(
+            token_program.key,
+            token_account.key,
+            token_mint.key,
+            &pda,
+            &[],
+            unsold,
+        )?;
+
+        invoke_signed(&burn_ix,
+            &[
+                token_account.clone(),
+                token_mint.clone(),
+                token_pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[addr[0], &[bump_seed]]],
+        )?;
+
+        msg!("Burnt {} unsold pool tokens", unsold);
+    }
+
+    fp.sold_token_count = fp.token_count.saturating_sub(fp.rm_token_count);
+    fp.rm_token_count = 0;
+    fp.is_raise_closed = true;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_vesting(schedule : VestingSchedule, start : i64, cliff : i64, duration : i64, 
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    // the investors bought under the current terms
+    if fp.investor_count() > 0 || fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::VestingLocked));
+    }
+
+    // the auction's claims send the tokens straight to the investors
+    if fp.pricing_mode == PricingMode::DutchAuction && schedule != VestingSchedule::None {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    if cliff < 0 || duration < 0 || 
+    (schedule == VestingSchedule::Linear && (duration == 0 || cliff > duration)) {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    fp.vesting_schedule = schedule;
+    fp.vesting_start = start;
+    fp.vesting_cliff = cliff;
+    fp.vesting_duration = duration;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+Creates the investor's vesting account and its token vault at their PDAs
+on the first purchase, the vault is owned by the pool's token_pda
+*/
+fn prepare_vesting<'a>(fp : &FundPool, fund_pool_account : &AccountInfo<'a>, investor : Pubkey,
+    payer : Payer<'_, 'a>, vesting : VestingAccounts<'_, 'a>, pool_token : PoolToken<'_, 'a>, 
+    program_id : &Pubkey) -> Result<VestingAccount, ProgramError> {
+
+    let Payer { signer_account, system_program, rent_account } = payer;
+    let VestingAccounts { vesting_account, vesting_vault } = vesting;
+    let PoolToken { token_mint, token_program, .. } = pool_token;
+
+    let vesting_seeds = &[VESTING_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (vesting_pda, vesting_bump) = Pubkey::find_program_address(vesting_seeds, program_id);
+
+    let vault_seeds = &[VESTING_VAULT_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(vault_seeds, program_id);
+
+    if *vesting_account.key != vesting_pda || *vesting_vault.key != vault_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidVestingAccount));
+    }
+
+    if !vesting_account.data_is_empty() {
+
+        is_account_program_owner(program_id, vesting_account)?;
+
+        return VestingAccount::unpack(&vesting_account.data.borrow());
+    }
+
+    let rent = Rent::from_account_info(rent_account)?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            vesting_account.key,
+            rent.minimum_balance(VestingAccount::LEN),
+            VestingAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            signer_account.clone(),
+            vesting_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[VESTING_SEED, fund_pool_account.key.as_ref(), investor.as_ref(), &[vesting_bump]]],
+    )?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            signer_account.key,
+            vesting_vault.key,
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            token_program.key,
+        ),
+        &[
+            signer_account.clone(),
+            vesting_vault.clone(),
+            system_program.clone(),
+        ],
+        &[&[VESTING_VAULT_SEED, fund_pool_account.key.as_ref(), investor.as_ref(), &[vault_bump]]],
+    )?;
+
+    invoke(
+        &initialize_account2(token_program.key, vesting_vault.key, token_mint.key, &fp.token_pda)?,
+        &[
+            vesting_vault.clone(),
+            token_mint.clone(),
+            rent_account.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let mut v = VestingAccount::new();
+    v.is_initialized = true;
+    v.fund_pool = *fund_pool_account.key;
+    v.investor = investor;
+    v.vault = *vesting_vault.key;
+
+    Ok(v)
+}
+
+
+/*
+Claims are allowed while the pool is paused
+*/
+fn claim_vested(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let vesting_account = next_account_info(account_info_iter)?;
+    let vesting_vault = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, vesting_account)?;
+
+    let fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+    let mut v = VestingAccount::unpack(&vesting_account.data.borrow())?;
+
+    if v.fund_pool != *fund_pool_account.key || v.vault != *vesting_vault.key ||
+    v.investor != *signer_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidVestingAccount));
+    }
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let dest = spl_token::state::Account::unpack(&investor_token_account.data.borrow())?;
+
+    if dest.mint != fp.token_mint || dest.owner != v.investor {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+
+    let claimable = fp.vested_amount(v.total_amount, now).saturating_sub(v.claimed_amount);
+
+    if claimable == 0 {
+
+        msg!("Nothing to claim yet");
+        return Ok(());
+    }
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, false, investor_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    let addr = &[fp.token_account.as_ref()];
+    let (_, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+    let ix = spl_token::instruction::transfer_checked(
+        token_program.key,
+        vesting_vault.key,
+        token_mint.key,
+        investor_token_account.key,
+        &fp.token_pda,
+        &[],
+        claimable,
+        fp.decimals,
+    )?;
+
+    invoke_signed(&ix,
+        &[
+            vesting_vault.clone(),
+            token_mint.clone(),
+            investor_token_account.clone(),
+            token_pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[addr[0], &[bump_seed]]],
+    )?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, true, investor_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    // the whole tokens released from the vault become available to the position
+    let multiplier = fp.token_multiplier().ok_or(PoolError::AmountsUnmatched)?;
+    let released = (v.claimed_amount + claimable) / multiplier - v.claimed_amount / multiplier;
+
+    let mut i = load_investor_record(&fp, signer_account.key, investor_account, program_id)?;
+
+    // the record is gone once the pool refunded it
+    if i.investor != Pubkey::default() {
+
+        i.vesting_token_count = i.vesting_token_count.saturating_sub(released);
+
+        Investor::pack(i, &mut investor_account.data.borrow_mut())?;
+    }
+
+    v.claimed_amount += claimable;
+
+    VestingAccount::pack(v, &mut vesting_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn revoke_mint_authority<'a>(token_program : &AccountInfo<'a>, token_mint : &AccountInfo<'a>, 
+    authority_account : &AccountInfo<'a>, signer_seeds : &[&[&[u8]]]) -> ProgramResult {
+
+    let ix = spl_token::instruction::set_authority(
+        token_program.key,
+        token_mint.key,
+        None,
+        spl_token::instruction::AuthorityType::MintTokens,
+        authority_account.key,
+        &[],
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[
+            token_mint.clone(),
+            authority_account.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+
+fn set_manager_signers(threshold : u8, signers : Vec<Pubkey>, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    fp.set_manager_signers(threshold, signers)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_required_verifier(verifier : Pubkey, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    fp.required_verifier = verifier;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The attestation is at the PDA of the verifier and the wallet, 
+so only the verifier signing here can create or renew it
+*/
+fn issue_attestation(wallet : Pubkey, jurisdiction : u16, expiry : i64, 
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let attestation_account = next_account_info(account_info_iter)?;
+    let verifier_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+
+    if !verifier_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+
+    if expiry <= now {
+
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    let seeds = &[ATTESTATION_SEED, verifier_account.key.as_ref(), wallet.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+
+    if *attestation_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    if attestation_account.data_is_empty() {
+
+        let rent = Rent::from_account_info(rent_account)?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                verifier_account.key,
+                attestation_account.key,
+                rent.minimum_balance(Attestation::LEN),
+                Attestation::LEN as u64,
+                program_id,
+            ),
+            &[
+                verifier_account.clone(),
+                attestation_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[ATTESTATION_SEED, verifier_account.key.as_ref(), wallet.as_ref(), &[bump_seed]]],
+        )?;
+    }
+
+    is_account_program_owner(program_id, attestation_account)?;
+
+    let mut a = Attestation::new();
+    a.is_initialized = true;
+    a.verifier = *verifier_account.key;
+    a.wallet = wallet;
+    a.jurisdiction = jurisdiction;
+    a.issued_at = now;
+    a.expiry = expiry;
+
+    Attestation::pack(a, &mut attestation_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn revoke_attestation(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let attestation_account = next_account_info(account_info_iter)?;
+    let verifier_account = next_account_info(account_info_iter)?;
+
+    if !verifier_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, attestation_account)?;
+
+    let a = Attestation::unpack(&attestation_account.data.borrow())?;
+
+    if a.verifier != *verifier_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    Attestation::pack(Attestation::new(), &mut attestation_account.data.borrow_mut())?;
+
+    let lamports = attestation_account.lamports();
+    **attestation_account.lamports.borrow_mut() = 0;
+    **verifier_account.lamports.borrow_mut() = verifier_account.lamports()
+    .checked_add(lamports).ok_or(PoolError::AmountsUnmatched)?;
+
+    Ok(())
+}
+
+
+fn check_attestation(verifier : &Pubkey, wallet : &Pubkey, attestation_account : &AccountInfo, 
+    program_id : &Pubkey) -> ProgramResult {
+
+    let seeds = &[ATTESTATION_SEED, verifier.as_ref(), wallet.as_ref()];
+    let (pda, _) = Pubkey::find_program_address(seeds, program_id);
+
+    if *attestation_account.key != pda || attestation_account.data_is_empty() {
+
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    is_account_program_owner(program_id, attestation_account)?;
+
+    let a = Attestation::unpack_unchecked(&attestation_account.data.borrow())?;
+
+    if !a.is_valid(verifier, wallet, Clock::get()?.unix_timestamp) {
+
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    Ok(())
+}
+
+
+fn set_pricing(mode : PricingMode, curve_param : u64, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.sold_count() > 0 || fp.investor_count() > 0 || fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::PricingLocked));
+    }
+
+    // the auction and the oracle have their own parameters, 
+    // see set_auction and set_oracle_pricing
+    if mode == PricingMode::DutchAuction || mode == PricingMode::Oracle {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    fp.pricing_mode = mode;
+    fp.curve_param = curve_param;
+
+    // the whole raise must be priceable
+    fp.purchase_cost(fp.token_count, Clock::get()?.unix_timestamp).ok_or(PoolError::InvalidPoolParameters)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_auction(start_price : u64, floor_price : u64, start : i64, end : i64,
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.sold_count() > 0 || fp.investor_count() > 0 || fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::PricingLocked));
+    }
+
+    if fp.is_vesting() {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    fp.set_auction(start_price, floor_price, start, end)?;
+
+    // the whole raise must be priceable at the start price
+    fp.token_count.checked_mul(start_price).ok_or(PoolError::InvalidPoolParameters)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+A sold out auction settles on its last bid, 
+an undersold one settles here at the floor price
+*/
+fn settle_auction(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    if fp.pricing_mode != PricingMode::DutchAuction {
+
+        return Err(ProgramError::from(PoolError::InvalidPricingMode));
+    }
+
+    if fp.is_auction_settled || Clock::get()?.unix_timestamp < fp.auction_end {
+
+        return Err(ProgramError::from(PoolError::AuctionNotActive));
+    }
+
+    fp.clearing_price = fp.auction_floor_price;
+    fp.is_auction_settled = true;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The bidder's gating accounts (see check_investor_gates) follow the rent,
+the limits apply to the token count of its bids
+*/
+fn place_bid(token_count : u64, allowlist : AllowlistProof, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let bid_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let pool_pda_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let config = load_program_config(program_id, config_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_not_paused(&config, &fp)?;
+
+    if fp.pricing_mode != PricingMode::DutchAuction {
+
+        return Err(ProgramError::from(PoolError::InvalidPricingMode));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+
+    if fp.is_auction_settled || fp.is_raise_closed || now < fp.auction_start || now >= fp.auction_end {
+
+        return Err(ProgramError::from(PoolError::AuctionNotActive));
+    }
+
+    if token_count == 0 || token_count > fp.rm_token_count {
+
+        return Err(ProgramError::from(PoolError::NotEnoughTokensLeft));
+    }
+
+    if *pool_pda_account.key != fp.pool_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolPda));
+    }
+
+    let price = fp.auction_price(now);
+    let lamports = token_count.checked_mul(price).ok_or(PoolError::AmountsUnmatched)?;
+
+    let seeds = &[BID_SEED, fund_pool_account.key.as_ref(), signer_account.key.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+
+    if *bid_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidBidAccount));
+    }
+
+    if bid_account.data_is_empty() {
+
+        let rent = Rent::from_account_info(rent_account)?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                signer_account.key,
+                bid_account.key,
+                rent.minimum_balance(Bid::LEN),
+                Bid::LEN as u64,
+                program_id,
+            ),
+            &[
+                signer_account.clone(),
+                bid_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[BID_SEED, fund_pool_account.key.as_ref(), signer_account.key.as_ref(), &[bump_seed]]],
+        )?;
+    }
+
+    is_account_program_owner(program_id, bid_account)?;
+
+    let mut b = Bid::unpack_unchecked(&bid_account.data.borrow())?;
+
+    if !b.is_initialized {
+
+        b.is_initialized = true;
+        b.fund_pool = *fund_pool_account.key;
+        b.bidder = *signer_account.key;
+    }
+
+    let position = b.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+    check_investor_gates(&fp, fund_pool_account, signer_account.key, position, 
+        &allowlist, account_info_iter, program_id)?;
+
+    invoke(
+        &system_instruction::transfer(signer_account.key, pool_pda_account.key, lamports),
+        &[
+            signer_account.clone(),
+            pool_pda_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    b.token_count += token_count;
+    b.locked_lamports = b.locked_lamports.checked_add(lamports).ok_or(PoolError::AmountsUnmatched)?;
+
+    fp.rm_token_count -= token_count;
+    fp.bid_lamports = fp.bid_lamports.checked_add(lamports).ok_or(PoolError::AmountsUnmatched)?;
+
+    // sold out, the last bid sets the clearing price
+    if fp.rm_token_count == 0 {
+
+        fp.clearing_price = price;
+        fp.is_auction_settled = true;
+    }
+
+    Bid::pack(b, &mut bid_account.data.borrow_mut())?;
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The bidder pays the clearing price for the token count of its bids, 
+the excess is refunded from the pool_pda and the position is 
+recorded like an investment, with the fees of a purchase on top 
+(see pay_purchase_fees) whose referral accounts come last
+*/
+fn claim_bid(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let bid_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let pool_pda_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let investor_pool_account = next_account_info(account_info_iter)?;
+    let investment_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let pool_token_account = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+    let ata_program = next_account_info(account_info_iter)?;
+    let manager_account = next_account_info(account_info_iter)?;
+    let treasury_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, bid_account)?;
+
+    let config = load_program_config(program_id, config_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+    let mut b = Bid::unpack(&bid_account.data.borrow())?;
+
+    if b.fund_pool != *fund_pool_account.key || b.bidder != *signer_account.key || b.is_claimed {
+
+        return Err(ProgramError::from(PoolError::InvalidBidAccount));
+    }
+
+    if !fp.is_auction_settled {
+
+        return Err(ProgramError::from(PoolError::AuctionNotSettled));
+    }
+
+    if *pool_pda_account.key != fp.pool_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolPda));
+    }
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda ||
+    *pool_token_account.key != fp.token_account {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let cost = b.token_count.checked_mul(fp.clearing_price).ok_or(PoolError::AmountsUnmatched)?;
+    let refund = b.locked_lamports.checked_sub(cost).ok_or(PoolError::AmountsUnmatched)?;
+
+    fp.bid_lamports = fp.bid_lamports.saturating_sub(b.locked_lamports);
+
+    let payer = Payer { signer_account, system_program, rent_account };
+    let pool_token = PoolToken { token_mint, token_pda_account, token_program };
+
+    let fees = FeeAccounts::new(&fp, manager_account, treasury_account, account_info_iter)?;
+
+    pay_purchase_fees(&fp, &config, cost, signer_account.key, payer, &fees, program_id)?;
+
+    if refund > 0 {
+
+        let pool_addr = &[fund_pool_account.key.as_ref()];
+        let (_, bump_seed) = Pubkey::find_program_address(pool_addr, program_id);
+
+        invoke_signed(
+            &system_instruction::transfer(pool_pda_account.key, signer_account.key, refund),
+            &[
+                pool_pda_account.clone(),
+                signer_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[pool_addr[0], &[bump_seed]]],
+        )?;
+    }
+
+    prepare_investor_token_account(&fp, *signer_account.key, payer, signer_account, 
+        investor_token_account, pool_token, ata_program)?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, false, investor_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    let token_to_tx = fp.token_amount(b.token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+    let addr = &[fp.token_account.as_ref()];
+    let (_, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+    let ix = if fp.mint_mode == MintMode::Lazy {
+
+        spl_token::instruction::mint_to_checked(
+            token_program.key,
+            token_mint.key,
+            investor_token_account.key,
+            &fp.token_pda,
+            &[],
+            token_to_tx,
+            fp.decimals,
+        )?
+    }
+    else {
+
+        spl_token::instruction::transfer_checked(
+            token_program.key,
+            pool_token_account.key,
+            token_mint.key,
+            investor_token_account.key,
+            &fp.token_pda,
+            &[],
+            token_to_tx,
+            fp.decimals,
+        )?
+    };
+
+    invoke_signed(&ix,
+        &[
+            pool_token_account.clone(),
+            token_mint.clone(),
+            investor_token_account.clone(),
+            token_pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[addr[0], &[bump_seed]]],
+    )?;
+
+    if fp.is_freezable {
+
+        set_token_account_frozen(&fp, true, investor_token_account, token_mint, 
+            token_pda_account, token_program, program_id)?;
+    }
+
+    let mut i = load_investor_record(&fp, signer_account.key, investor_account, program_id)?;
+
+    if i.investor == Pubkey::default() {
+
+        i.investor = *signer_account.key;
+        i.address = *investor_account.key;
+        i.pool_address = fp.address;
+        i.date = Clock::get()?.unix_timestamp;
+    }
+
+    i.amount = i.amount.checked_add(cost).ok_or(PoolError::AmountsUnmatched)?;
+    i.token_count = i.token_count.checked_add(b.token_count).ok_or(PoolError::AmountsUnmatched)?;
+    i.token_account = *investor_token_account.key;
+
+    record_investment(&mut i, cost, b.token_count, fund_pool_account, investment_account, 
+        payer, program_id)?;
+
+    fp.set_investor_token_count(i.investor, i.address, i.token_count)?;
+
+    register_address_to_user_pool(*investor_account.key, *signer_account.key, investor_pool_account);
+
+    b.is_claimed = true;
+
+    Investor::pack(i, &mut investor_account.data.borrow_mut())?;
+    Bid::pack(b, &mut bid_account.data.borrow_mut())?;
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_price_tiers(tiers : Vec<PriceTier>, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.sold_count() > 0 || fp.investor_count() > 0 || fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::PricingLocked));
+    }
+
+    fp.set_price_tiers(tiers)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The investor buys the token count for at most the amount, 
+only the computed cost is charged
+*/
+fn check_purchase_amount(cost : u64, amount : u64, expiry : i64, now : i64) -> ProgramResult {
+
+    if expiry != 0 && now > expiry {
+
+        return Err(ProgramError::from(PoolError::InstructionExpired));
+    }
+
+    if cost > amount {
+
+        return Err(ProgramError::from(PoolError::PriceAboveMax));
+    }
+
+    Ok(())
+}
+
+
+fn set_oracle_pricing(oracle : Pubkey, oracle_program : Pubkey, usd_price : u64, max_age : i64, max_conf_bps : u16,
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if fp.sold_count() > 0 || fp.investor_count() > 0 || fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::PricingLocked));
+    }
+
+    fp.set_oracle_pricing(oracle, oracle_program, usd_price, max_age, max_conf_bps)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The pool's SOL/USD price, refused when the feed isn't the pool's 
+or isn't owned by the oracle program, or when stale or too uncertain
+*/
+fn load_price_feed(fp : &FundPool, price_feed_account : &AccountInfo, now : i64) -> Result<PriceFeed, ProgramError> {
+
+    if *price_feed_account.key != fp.price_oracle || *price_feed_account.owner != fp.oracle_program {
+
+        return Err(ProgramError::from(PoolError::InvalidOracleAccount));
+    }
+
+    let feed = PriceFeed::unpack_unchecked(&price_feed_account.data.borrow())
+    .map_err(|_| PoolError::InvalidOracleAccount)?;
+
+    feed.check(now, fp.oracle_max_age, fp.oracle_max_conf_bps)?;
+
+    Ok(feed)
+}
+
+
+fn set_referral_bps(referral_bps : u16, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if referral_bps > MAX_BASIS_POINTS {
+
+        return Err(ProgramError::from(PoolError::InvalidPoolParameters));
+    }
+
+    fp.referral_bps = referral_bps;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The signer paying for what's created or transferred on 
+its behalf, with the system program and the rent sysvar
+*/
+#[derive(Clone, Copy)]
+struct Payer<'a, 'b> {
+
+    signer_account : &'a AccountInfo<'b>,
+
+    system_program : &'a AccountInfo<'b>,
+
+    rent_account : &'a AccountInfo<'b>,
+}
+
+
+/*
+The pool's mint, its token_pda which is the authority of the pool's vault,
+of the vesting vaults and of the freezes, and the token program
+*/
+#[derive(Clone, Copy)]
+struct PoolToken<'a, 'b> {
+
+    token_mint : &'a AccountInfo<'b>,
+
+    token_pda_account : &'a AccountInfo<'b>,
+
+    token_program : &'a AccountInfo<'b>,
+}
+
+
+// the investor's vesting account and the vault holding its vesting tokens
+#[derive(Clone, Copy)]
+struct VestingAccounts<'a, 'b> {
+
+    vesting_account : &'a AccountInfo<'b>,
+
+    vesting_vault : &'a AccountInfo<'b>,
+}
+
+
+/*
+Where the fees of a purchase go, the referrer and its referral 
+account are read only when the pool pays referrals
+*/
+#[derive(Clone, Copy)]
+struct FeeAccounts<'a, 'b> {
+
+    manager_account : &'a AccountInfo<'b>,
+
+    treasury_account : &'a AccountInfo<'b>,
+
+    referral : Option<(&'a AccountInfo<'b>, &'a AccountInfo<'b>)>,
+}
+
+impl<'a, 'b> FeeAccounts<'a, 'b> {
+
+    fn new(fp : &FundPool, manager_account : &'a AccountInfo<'b>, treasury_account : &'a AccountInfo<'b>,
+        account_info_iter : &mut std::slice::Iter<'a, AccountInfo<'b>>) -> Result<Self, ProgramError> {
+
+        let referral = if fp.referral_bps > 0 {
+
+            Some((next_account_info(account_info_iter)?, next_account_info(account_info_iter)?))
+        }
+        else {
+
+            None
+        };
+
+        Ok(FeeAccounts { manager_account, treasury_account, referral })
+    }
+}
+
+
+/*
+The manager's fee less the referrer's share, and the protocol fee on the
+cost, paid by the signer on top of the cost of a purchase. The investor 
+or the signer as the referrer means no referrer
+*/
+fn pay_purchase_fees<'a>(fp : &FundPool, config : &ProgramConfig, cost : u64, investor : &Pubkey,
+    payer : Payer<'_, 'a>, fees : &FeeAccounts<'_, 'a>, program_id : &Pubkey) -> ProgramResult {
+
+    let Payer { signer_account, system_program, .. } = payer;
+    let FeeAccounts { manager_account, treasury_account, referral } = *fees;
+
+    let mut referral_reward = 0;
+
+    if let Some((referrer_account, referral_account)) = referral {
+
+        if referrer_account.key != investor && referrer_account.key != signer_account.key {
+
+            referral_reward = fp.referral_reward().ok_or(PoolError::AmountsUnmatched)?;
+
+            pay_referral(referral_reward, cost, referrer_account, referral_account, payer, program_id)?;
+        }
+    }
+
+    if fp.fee_in_lamports > referral_reward {
+
+        // transfer the fee or commission to the manager 
+        if *manager_account.key != fp.manager {
+
+            return Err( ProgramError::from( PoolError::InvalidManagerAccount) );
+        }
+
+        invoke(
+            &system_instruction::transfer(signer_account.key, &fp.manager, fp.fee_in_lamports - referral_reward),
+            &[
+                signer_account.clone(),
+                manager_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    // the protocol fee goes to the treasury alongside the manager's fee 
+    let protocol_fee = config.protocol_fee(cost).ok_or(PoolError::AmountsUnmatched)?;
+
+    if protocol_fee > 0 {
+
+        if *treasury_account.key != config.treasury {
+
+            return Err( ProgramError::from( PoolError::InvalidTreasuryAccount) );
+        }
+
+        invoke(
+            &system_instruction::transfer(signer_account.key, treasury_account.key, protocol_fee),
+            &[
+                signer_account.clone(),
+                treasury_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+
+/*
+Pays the referrer its share of the manager's fee and adds to its totals,
+the referral account is created at its PDA on the first referral
+*/
+fn pay_referral<'a>(reward : u64, invested : u64, referrer_account : &AccountInfo<'a>, 
+    referral_account : &AccountInfo<'a>, payer : Payer<'_, 'a>, program_id : &Pubkey) -> ProgramResult {
+
+    let Payer { signer_account, system_program, rent_account } = payer;
+
+    let seeds = &[REFERRAL_SEED, referrer_account.key.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+
+    if *referral_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidReferralAccount));
+    }
+
+    if referral_account.data_is_empty() {
+
+        let rent = Rent::from_account_info(rent_account)?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                signer_account.key,
+                referral_account.key,
+                rent.minimum_balance(Referral::LEN),
+                Referral::LEN as u64,
+                program_id,
+            ),
+            &[
+                signer_account.clone(),
+                referral_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[REFERRAL_SEED, referrer_account.key.as_ref(), &[bump_seed]]],
+        )?;
+    }
+
+    is_account_program_owner(program_id, referral_account)?;
+
+    let mut r = Referral::unpack_unchecked(&referral_account.data.borrow())?;
+
+    if !r.is_initialized {
+
+        r.is_initialized = true;
+        r.referrer = *referrer_account.key;
+    }
+
+    if reward > 0 {
+
+        invoke(
+            &system_instruction::transfer(signer_account.key, referrer_account.key, reward),
+            &[
+                signer_account.clone(),
+                referrer_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    r.referral_count += 1;
+    r.referred_lamports = r.referred_lamports.saturating_add(invested);
+    r.reward_lamports = r.reward_lamports.saturating_add(reward);
+
+    Referral::pack(r, &mut referral_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_allowlist_root(root : [u8; 32], program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    fp.allowlist_root = root;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+fn set_investor_limits(min_tokens : u64, max_tokens : u64, tiers : Vec<WhitelistTier>, 
+    program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    fp.set_investor_limits(min_tokens, max_tokens, tiers)?;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The whitelist entry is created at its PDA on first use, 
+paid by the signer following it
+*/
+fn set_whitelist_tier(investor : Pubkey, tier : u8, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let whitelist_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    if tier as usize > fp.tiers().len() {
+
+        return Err(ProgramError::from(PoolError::InvalidWhitelistTier));
+    }
+
+    let seeds = &[WHITELIST_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+
+    if *whitelist_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidWhitelistAccount));
+    }
+
+    if whitelist_account.data_is_empty() {
+
+        if !signer_account.is_signer {
+
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let rent = Rent::from_account_info(rent_account)?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                signer_account.key,
+                whitelist_account.key,
+                rent.minimum_balance(WhitelistEntry::LEN),
+                WhitelistEntry::LEN as u64,
+                program_id,
+            ),
+            &[
+                signer_account.clone(),
+                whitelist_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[WHITELIST_SEED, fund_pool_account.key.as_ref(), investor.as_ref(), &[bump_seed]]],
+        )?;
+    }
+
+    is_account_program_owner(program_id, whitelist_account)?;
+
+    let mut w = WhitelistEntry::new();
+    w.is_initialized = true;
+    w.fund_pool = *fund_pool_account.key;
+    w.investor = investor;
+    w.tier = tier;
+
+    WhitelistEntry::pack(w, &mut whitelist_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The investor's whitelist tier, an investor without 
+a whitelist entry is in tier 0
+*/
+fn load_whitelist_tier(fund_pool_account : &AccountInfo, investor : &Pubkey, 
+    whitelist_account : &AccountInfo, program_id : &Pubkey) -> Result<u8, ProgramError> {
+
+    let seeds = &[WHITELIST_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (pda, _) = Pubkey::find_program_address(seeds, program_id);
+
+    if *whitelist_account.key != pda {
+
+        return Err(ProgramError::from(PoolError::InvalidWhitelistAccount));
+    }
+
+    if whitelist_account.data_is_empty() {
+
+        return Ok(0);
+    }
+
+    is_account_program_owner(program_id, whitelist_account)?;
+
+    let w = WhitelistEntry::unpack(&whitelist_account.data.borrow())?;
+
+    Ok(w.tier)
+}
+
+
+/*
+The accounts after the token program are the signer count manager (or admin) 
+signers followed by, for each investor, the investor account, the investor's 
+wallet and its token account, then its vesting account and vesting vault when 
+the pool vests. Each investor is refunded the amount kept in the pool_pda
+without signing, its unvested tokens are burnt from the vesting vault and 
+its token account is frozen when the pool is freezable, the tokens left 
+outside are worthless once the pool is deleted. Can be called in batches, 
+once no investor is left, the pool can be deleted
+*/
+fn wind_down_fund_pool(signer_count : u8, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    msg!("Winding down fund pool...");
+
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let pool_pda_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_pda_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    let signers = accounts.get(6..6 + signer_count as usize).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    for _ in 0..signer_count {
+
+        next_account_info(account_info_iter)?;
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager_or_admin(&fp, program_id, config_account, signers)?;
+
+    if *token_mint.key != fp.token_mint || *token_pda_account.key != fp.token_pda {
+
+        return Err(ProgramError::from(PoolError::InvalidTokenAccount));
+    }
+
+    let pool_addr = &[fund_pool_account.key.as_ref()];
+    let (pool_pda, bump_seed) = Pubkey::find_program_address(pool_addr, program_id);
+
+    if *pool_pda_account.key != pool_pda || pool_pda != fp.pool_pda {
+
+        return Err(ProgramError::from(PoolError::UnmatchedPoolAddress));
+    }
+
+    while let Ok(investor_account) = next_account_info(account_info_iter) {
+
+        let investor_wallet = next_account_info(account_info_iter)?;
+        let investor_token_account = next_account_info(account_info_iter)?;
+
+        is_account_program_owner(program_id, investor_account)?;
+
+        let i = Investor::unpack_unchecked(&investor_account.data.borrow())?;
+
+        if i.pool_address != fp.address {
+
+            return Err(ProgramError::from(PoolError::UnmatchedPoolAddress));
+        }
+
+        if i.investor != *investor_wallet.key {
+
+            return Err(ProgramError::from(PoolError::UnmatchedInvestorAccountAddress));
+        }
+
+        let to_burn = fp.token_amount(i.token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+        if fp.is_vesting() {
+
+            let vesting_account = next_account_info(account_info_iter)?;
+            let vesting_vault = next_account_info(account_info_iter)?;
+
+            burn_vesting_vault(&fp, fund_pool_account, &i.investor, to_burn, 
+                VestingAccounts { vesting_account, vesting_vault }, 
+                PoolToken { token_mint, token_pda_account, token_program }, program_id)?;
+        }
+
+        // the token_pda is the freeze authority, not the holder's
+        if fp.is_freezable {
+
+            check_holder_token_account(&fp, investor_wallet.key, investor_token_account)?;
+
+            set_token_account_frozen(&fp, true, investor_token_account, token_mint, 
+                token_pda_account, token_program, program_id)?;
+        }
+
+        fp.remove_investor(*investor_account.key)?;
+
+        if i.amount > 0 {
+
+            invoke_signed(
+                &system_instruction::transfer(&pool_pda, investor_wallet.key, i.amount),
+                &[
+                    pool_pda_account.clone(),
+                    investor_wallet.clone(),
+                    system_program.clone(),
+                ],
+                &[&[pool_addr[0], &[bump_seed]]],
+            )?;
+        }
+
+        let zeros = &vec![0; investor_account.data_len()];
+
+        investor_account.data.borrow_mut()[0..zeros.len()].copy_from_slice(zeros);
+
+        msg!("Refunded {} lamports to investor :{:?}", i.amount, i.investor);
+    }
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+
+/*
+The pools of the original layout can't be resized, so the client creates
+a new program owned account of FundPool::LEN and the pool is copied into it,
+the lamports of the original pool_pda move to the new one as the pool_pda 
+is derived from the pool account. The manager's user pool and the market 
+follow the new address, the original account is closed to the manager
+*/
+fn migrate_fund_pool(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    msg!("Migrating fund pool...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let legacy_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let legacy_pool_pda_account = next_account_info(account_info_iter)?;
+    let pool_pda_account = next_account_info(account_info_iter)?;
+    let user_pool_account = next_account_info(account_info_iter)?;
+    let market_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, legacy_account)?;
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_legacy(&legacy_account.data.borrow())?;
+
+    if !fp.is_initialized {
+
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if fp.manager != *signer_account.key {
+
+        return Err(ProgramError::from(PoolError::InvalidManagerAccount));
+    }
+
+    if FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?.is_initialized {
+
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let legacy_addr = &[legacy_account.key.as_ref()];
+    let (legacy_pool_pda, bump_seed) = Pubkey::find_program_address(legacy_addr, program_id);
+
+    let pool_addr = &[fund_pool_account.key.as_ref()];
+    let (pool_pda, _) = Pubkey::find_program_address(pool_addr, program_id);
+
+    if *legacy_pool_pda_account.key != fp.pool_pda || legacy_pool_pda != fp.pool_pda || 
+    *pool_pda_account.key != pool_pda {
+
+        return Err(ProgramError::from(PoolError::UnmatchedPoolAddress));
+    }
+
+    let lamports = legacy_pool_pda_account.lamports();
+
+    if lamports > 0 {
+
+        invoke_signed(
+            &system_instruction::transfer(&legacy_pool_pda, &pool_pda, lamports),
+            &[
+                legacy_pool_pda_account.clone(),
+                pool_pda_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[legacy_addr[0], &[bump_seed]]],
+        )?;
+    }
+
+    let legacy_address = fp.address;
+
+    fp.address = *fund_pool_account.key;
+    fp.pool_pda = pool_pda;
+
+    FundPool::pack(fp.clone(), &mut fund_pool_account.data.borrow_mut())?;
+
+    let zeros = &vec![0; legacy_account.data_len()];
+    legacy_account.data.borrow_mut()[0..zeros.len()].copy_from_slice(zeros);
+
+    let rent = legacy_account.lamports();
+    **legacy_account.lamports.borrow_mut() = 0;
+    **signer_account.lamports.borrow_mut() = signer_account.lamports()
+    .checked_add(rent).ok_or(PoolError::AmountsUnmatched)?;
+
+    if user_pool_account.owner == program_id  {
+
+        remove_address_from_user_pool(legacy_address, fp.manager, user_pool_account);
+    }
+
+    if market_account.owner == program_id  {
+
+        remove_address_from_market(legacy_address, market_account);
+    }
+
+    register_new_fund_pool(fp.address, fp.manager, fp.is_finalized, 
+        user_pool_account, market_account, program_id);
+
+    Ok(())
+}
+
+
+/*
+An investor record of the original layout is copied into a new account 
+of Investor::LEN created by the client, the record must be in the 
+migrated pool's registry, which is moved to the new address along with
+the investor's user pool. Either the investor or the manager signs
+*/
+fn migrate_investor(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let legacy_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let investor_wallet = next_account_info(account_info_iter)?;
+    let user_pool_account = next_account_info(account_info_iter)?;
+    let signer_account = next_account_info(account_info_iter)?;
+
+    if !signer_account.is_signer {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+    is_account_program_owner(program_id, legacy_account)?;
+    is_account_program_owner(program_id, investor_account)?;
+
+    let mut fp = FundPool::unpack(&fund_pool_account.data.borrow())?;
+    let mut i = Investor::unpack_legacy(&legacy_account.data.borrow())?;
+
+    if *investor_wallet.key != i.investor {
+
+        return Err(ProgramError::from(PoolError::InvestorNotFound));
+    }
+
+    if *signer_account.key != i.investor && *signer_account.key != fp.manager {
+
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if Investor::unpack_unchecked(&investor_account.data.borrow())?.investor != Pubkey::default() {
+
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    i.pool_address = fp.address;
+    i.address = *investor_account.key;
+
+    // the record must be in the migrated pool's registry
+    if fp.remove_investor(*legacy_account.key)?.investor != i.investor {
+
+        return Err(ProgramError::from(PoolError::InvestorNotFound));
+    }
+
+    fp.set_investor_token_count(i.investor, i.address, i.token_count)?;
+
+    Investor::pack(i.clone(), &mut investor_account.data.borrow_mut())?;
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
 
-                register_address_to_user_pool(address, manager, user_pool_account)
-            }
-        
-           
-            if market_account.owner == program_id && is_finalized  {
+    let zeros = &vec![0; legacy_account.data_len()];
+    legacy_account.data.borrow_mut()[0..zeros.len()].copy_from_slice(zeros);
 
-                register_address_to_market(address, market_account)
-            }
+    let rent = legacy_account.lamports();
+    **legacy_account.lamports.borrow_mut() = 0;
+    **investor_wallet.lamports.borrow_mut() = investor_wallet.lamports()
+    .checked_add(rent).ok_or(PoolError::AmountsUnmatched)?;
 
-          
-        }
-    
+    if user_pool_account.owner == program_id  {
+
+        remove_address_from_user_pool(*legacy_account.key, i.investor, user_pool_account);
+        register_address_to_user_pool(i.address, i.investor, user_pool_account);
     }
+
     Ok(())
 }
 
 
+
 /*
-Maybe needed in the future, currently isn't called by client
+Burns up to the token amount from the investor's vesting vault and 
+resets its vesting account, returns the amount burnt
 */
-fn update_fund_pool(manager : Pubkey,
-    address : Pubkey,fee_in_lamports : u64,token_count : u64, 
-    token_to_lamport_ratio : u64, 
-    is_finalized : bool,
-    icon : u16, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+fn burn_vesting_vault<'a>(fp : &FundPool, fund_pool_account : &AccountInfo<'a>, investor : &Pubkey, 
+    token_amount : u64, vesting : VestingAccounts<'_, 'a>, pool_token : PoolToken<'_, 'a>, 
+    program_id : &Pubkey) -> Result<u64, ProgramError> {
 
-    let account_info_iter = &mut accounts.iter();
+    let VestingAccounts { vesting_account, vesting_vault } = vesting;
+    let PoolToken { token_mint, token_pda_account, token_program } = pool_token;
 
-    let account = next_account_info(account_info_iter)?;
+    let vesting_seeds = &[VESTING_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (vesting_pda, _) = Pubkey::find_program_address(vesting_seeds, program_id);
 
-    if is_account_program_owner(program_id, account).unwrap() {
+    let vault_seeds = &[VESTING_VAULT_SEED, fund_pool_account.key.as_ref(), investor.as_ref()];
+    let (vault_pda, _) = Pubkey::find_program_address(vault_seeds, program_id);
 
-        let mut w = FundPool::unpack_unchecked(&account.data.borrow())?;
+    if *vesting_account.key != vesting_pda || *vesting_vault.key != vault_pda {
 
-        if w.manager == manager && w.address == address {
-            w.token_count = token_count;
-            w.token_to_lamport_ratio = token_to_lamport_ratio; 
-            w.is_finalized = is_finalized;
-            w.fee_in_lamports = fee_in_lamports;
-            w.icon = icon;
-            FundPool::pack(w, &mut account.data.borrow_mut())?;
-        }
-        else {
+        return Err(ProgramError::from(PoolError::InvalidVestingAccount));
+    }
 
-            msg!("No update, different manager, can't change manager!!");
-        }
-       
+    // the position never vested, e.g. bought on the order book
+    if vesting_account.data_is_empty() {
+
+        return Ok(0);
+    }
+
+    is_account_program_owner(program_id, vesting_account)?;
+
+    let mut v = VestingAccount::unpack(&vesting_account.data.borrow())?;
+
+    if v.vault != *vesting_vault.key {
+
+        return Err(ProgramError::from(PoolError::InvalidVestingAccount));
+    }
+
+    let amount = spl_token::state::Account::unpack(&vesting_vault.data.borrow())?.amount.min(token_amount);
+
+    if amount > 0 {
+
+        let addr = &[fp.token_account.as_ref()];
+        let (_, bump_seed) = Pubkey::find_program_address(addr, program_id);
+
+        invoke_signed(
+            &spl_token::instruction::burn(
+                token_program.key,
+                vesting_vault.key,
+                token_mint.key,
+                &fp.token_pda,
+                &[],
+                amount,
+            )?,
+            &[
+                vesting_vault.clone(),
+                token_mint.clone(),
+                token_pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[addr[0], &[bump_seed]]],
+        )?;
     }
+
+    v.total_amount = 0;
+    v.claimed_amount = 0;
+
+    VestingAccount::pack(v, &mut vesting_account.data.borrow_mut())?;
+
+    Ok(amount)
+}
+
+
+fn propose_manager(new_manager : Pubkey, program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let fund_pool_account = next_account_info(account_info_iter)?;
+
+    is_account_program_owner(program_id, fund_pool_account)?;
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    check_pool_manager(&fp, accounts)?;
+
+    fp.pending_manager = new_manager;
+
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
+
     Ok(())
 }
 
+
 /*
-Maybe needed in the future
+The pool is moved from the old manager's user pool to the new manager's,
+and the fee_in_lamports goes to the new manager from now on
 */
-fn delete_fund_pool(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
-
+fn accept_manager(program_id: &Pubkey,accounts: &[AccountInfo]) -> ProgramResult {
 
-    msg!("Deleting fund pool...");
-    
     let account_info_iter = &mut accounts.iter();
 
-    let account = next_account_info(account_info_iter)?;
-    let user_pool_account = next_account_info(account_info_iter)?;
-    let market_account = next_account_info(account_info_iter)?;
+    let fund_pool_account = next_account_info(account_info_iter)?;
+    let old_user_pool_account = next_account_info(account_info_iter)?;
+    let new_user_pool_account = next_account_info(account_info_iter)?;
     let signer_account = next_account_info(account_info_iter)?;
 
-
-    // check for signer
     if !signer_account.is_signer {
 
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    is_account_program_owner(program_id, fund_pool_account)?;
 
-    if is_account_program_owner(program_id, account).unwrap() {
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
 
+    if fp.pending_manager == Pubkey::default() || fp.pending_manager != *signer_account.key {
 
-        let fund_pool = FundPool::unpack_unchecked(&account.data.borrow())?;
+        return Err(ProgramError::from(PoolError::InvalidManagerAccount));
+    }
 
-    
-        let zeros = &vec![0; account.data_len()];
+    let old_manager = fp.manager;
+    let address = fp.address;
 
-        account.data.borrow_mut()[0..zeros.len()].copy_from_slice(zeros);
+    fp.manager = fp.pending_manager;
+    fp.pending_manager = Pubkey::default();
+    // the new manager signs alone until a new manager set is configured
+    fp.set_manager_signers(0, Vec::new())?;
 
-       
-        if user_pool_account.owner == program_id  {
+    let new_manager = fp.manager;
 
-            remove_address_from_user_pool(fund_pool.address, fund_pool.manager, user_pool_account)
-        }
-        
-       
-        if market_account.owner == program_id  {
+    FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut())?;
 
-            remove_address_from_market(fund_pool.address, market_account)
-        }
-      
+    if old_user_pool_account.owner == program_id  {
+
+        remove_address_from_user_pool(address, old_manager, old_user_pool_account)
+    }
+
+    if new_user_pool_account.owner == program_id  {
 
+        register_address_to_user_pool(address, new_manager, new_user_pool_account)
     }
+
     Ok(())
 }
 
@@ -632,12 +4282,76 @@ fn remove_address_from_user_pool(address : Pubkey, user : Pubkey, user_pool_acco
  
 }
 
+/*
+The investor token account must be the investor's associated token account
+of the pool token mint, which is created here (paid by the signer) if missing 
+*/
+fn prepare_investor_token_account<'a>(fp : &FundPool, investor : Pubkey, payer : Payer<'_, 'a>, 
+    investor_wallet : &AccountInfo<'a>, investor_token_account : &AccountInfo<'a>, 
+    pool_token : PoolToken<'_, 'a>, ata_program : &AccountInfo<'a>) -> ProgramResult {
+
+    let Payer { signer_account, system_program, rent_account } = payer;
+    let PoolToken { token_mint, token_program, .. } = pool_token;
+
+    if *investor_wallet.key != investor {
+
+        return Err( ProgramError::from( PoolError::UnmatchedInvestorAccountAddress) );
+    }
+
+    let ata = get_associated_token_address(&investor, &fp.token_mint);
+
+    if *investor_token_account.key != ata {
+
+        return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
+    }
+
+    if investor_token_account.data_is_empty() {
+
+        if *ata_program.key != spl_associated_token_account::id() {
+
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        invoke(
+            &create_associated_token_account(signer_account.key, &investor, &fp.token_mint),
+            &[
+                signer_account.clone(),
+                investor_token_account.clone(),
+                investor_wallet.clone(),
+                token_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                rent_account.clone(),
+                ata_program.clone(),
+            ],
+        )?;
+    }
+
+    if *investor_token_account.owner != spl_token::id() {
+
+        return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
+    }
+
+    let token_account = spl_token::state::Account::unpack(&investor_token_account.data.borrow())?;
+
+    if token_account.mint != fp.token_mint || token_account.owner != investor {
+
+        return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
+    }
+
+    Ok(())
+}
+
+
 fn add_investor(investor : Pubkey,
     pool_address : Pubkey,
     address : Pubkey, 
-    amount : u64,token_count : u64, date : i64,
+    amount : u64,token_count : u64, date : i64, expiry : i64, allowlist : AllowlistProof,
     program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult{
 
+    // the config account is always the last one
+    let (config_account, accounts) = split_config_account(accounts)?;
+
     let account_info_iter = &mut accounts.iter();
 
     let investor_account = next_account_info(account_info_iter)?;
@@ -652,7 +4366,35 @@ fn add_investor(investor : Pubkey,
     let investor_token_account = next_account_info(account_info_iter)?;
     let pool_token_account = next_account_info(account_info_iter)?; 
     let token_program = next_account_info(account_info_iter)?;
+    let treasury_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let investor_wallet = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+    let ata_program = next_account_info(account_info_iter)?;
+    let investment_account = next_account_info(account_info_iter)?;
+
+
+    let config = load_program_config(program_id, config_account)?;
+
+    if fund_pool_account.owner != program_id {
+
+        return Err(ProgramError::IncorrectProgramId);   
+    }
+
+    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
+
+    // no new investment while the program or the pool is paused
+    check_not_paused(&config, &fp)?;
+
+    if fp.is_raise_closed {
+
+        return Err(ProgramError::from(PoolError::RaiseClosed));
+    }
 
+    if token_count > fp.rm_token_count {
+
+        return Err(ProgramError::from(PoolError::NotEnoughTokensLeft));
+    }
 
    
      // check for signer
@@ -673,14 +4415,7 @@ fn add_investor(investor : Pubkey,
         return Err(ProgramError::IncorrectProgramId);
     }
 
-    if fund_pool_account.owner != program_id {
-
-        return Err(ProgramError::IncorrectProgramId);   
-    }
-
     
-    let mut fp = FundPool::unpack_unchecked(&fund_pool_account.data.borrow())?;
-
     if fp.address != pool_address{
 
         return Err( ProgramError::from( PoolError::UnmatchedPoolAddress) );
@@ -695,24 +4430,61 @@ fn add_investor(investor : Pubkey,
     }
 
 
-    let mut i = Investor::new();
-    i.investor = investor;
-    i.amount = amount;
-    i.date = date;
-    i.token_count = token_count;
-    i.address = address;
-    i.pool_address = pool_address;
-   
- 
-    let token_to_lamport_ratio = fp.token_to_lamport_ratio;
-    let amount_in_lamports = token_to_lamport_ratio * token_count;
+    // the auction only takes bids
+    if fp.pricing_mode == PricingMode::DutchAuction {
+
+        return Err(ProgramError::from(PoolError::InvalidPricingMode));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+
+    // the price feed follows only when the pool is oracle priced
+    let amount_in_lamports = if fp.pricing_mode == PricingMode::Oracle {
+
+        let price_feed_account = next_account_info(account_info_iter)?;
+
+        let feed = load_price_feed(&fp, price_feed_account, now)?;
+
+        fp.oracle_purchase_cost(token_count, &feed)
+    }
+    else {
+
+        fp.purchase_cost(token_count, now)
+    }
+    .ok_or(PoolError::AmountsUnmatched)?;
+
+    // the price may have changed since the client signed, the amount 
+    // caps what's paid and the unspent part never leaves the signer
+    check_purchase_amount(amount_in_lamports, amount, expiry, now)?;
+
+    // a top-up accumulates into the investor's existing record
+    let mut i = load_investor_record(&fp, &investor, investor_account, program_id)?;
+
+    if i.investor == Pubkey::default() {
 
+        i.investor = investor;
+        i.date = date;
+        i.address = address;
+        i.pool_address = pool_address;
+    }
+
+    i.amount = i.amount.checked_add(amount_in_lamports).ok_or(PoolError::AmountsUnmatched)?;
+    i.token_count = i.token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+    check_investor_gates(&fp, fund_pool_account, &investor, i.token_count, &allowlist, 
+        account_info_iter, program_id)?;
+
+    // the attested or invited investor signs for itself
+    if fp.required_verifier != Pubkey::default() && *signer_account.key != investor {
 
-    // check the amount to prevent faking from client's side
-    if amount_in_lamports != amount{
+        return Err(ProgramError::from(PoolError::InvalidAttestation));
+    }
+
+    if fp.has_allowlist() && *signer_account.key != investor {
 
-        return Err(ProgramError::from(PoolError::AmountsUnmatched));
+        return Err(ProgramError::from(PoolError::NotAllowlisted));
     }
+   
 
     /*
     Transfer lamports which is the fund in SOL of the fund pool to PDA (escrow)
@@ -737,29 +4509,12 @@ fn add_investor(investor : Pubkey,
     */
 
     
-    if fp.fee_in_lamports > 0 {
-
-        // transfer the fee or commission to the manager 
-        let manager = fp.manager.clone();
-
-       
-        if *manager_account.key != manager {
-
-            return Err( ProgramError::from( PoolError::InvalidManagerAccount) );
+    let payer = Payer { signer_account, system_program, rent_account };
+    let pool_token = PoolToken { token_mint, token_pda_account : pool_token_pda, token_program };
 
-        }
+    let fees = FeeAccounts::new(&fp, manager_account, treasury_account, account_info_iter)?;
 
-        invoke(
-            &system_instruction::transfer(signer_account.key, &manager, fp.fee_in_lamports),
-            &[
-                signer_account.clone(),
-                manager_account.clone(),
-                system_program.clone(),
-            ],
-        )?;
-    
-       
-    }
+    pay_purchase_fees(&fp, &config, amount_in_lamports, &investor, payer, &fees, program_id)?;
 
  
     // transfer the token to investor
@@ -775,39 +4530,133 @@ fn add_investor(investor : Pubkey,
 
     //msg!("pda.found:{:?}",pda);
 
+    // only the pool's own vault, pools may share a mint
+    if *pool_token_account.key != fp.token_account || pda != fp.token_pda {
 
-    let token_to_tx = token_count * TOKEN_MULTIPLIER;
-    
-    
-    let tf_to_inv_ix = spl_token::instruction::transfer(
-        token_program.key,
-        pool_token_account.key,
-        investor_token_account.key,
-        &pda,
-        &[&pda],
-        token_to_tx,
-    )?;
+        return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
+    }
+
+    if *token_mint.key != fp.token_mint {
+
+        return Err( ProgramError::from( PoolError::InvalidTokenAccount) );
+    }
+
+    let mint = spl_token::state::Mint::unpack(&token_mint.data.borrow())?;
+
+    if mint.decimals != fp.decimals {
+
+        return Err( ProgramError::from( PoolError::DecimalsMismatch) );
+    }
+
+    let token_to_tx = fp.token_amount(token_count).ok_or(PoolError::AmountsUnmatched)?;
+
+    // vesting pools hold the tokens in the investor's vesting vault, 
+    // whose accounts follow only when the pool vests
+    let mut vesting = None;
+    let mut token_dest_account = investor_token_account;
+
+    if fp.is_vesting() {
+
+        let vesting_account = next_account_info(account_info_iter)?;
+        let vesting_vault = next_account_info(account_info_iter)?;
+
+        let v = prepare_vesting(&fp, fund_pool_account, investor, payer, 
+            VestingAccounts { vesting_account, vesting_vault }, pool_token, program_id)?;
+
+        vesting = Some((v, vesting_account));
+        token_dest_account = vesting_vault;
+
+        i.vesting_token_count = i.vesting_token_count.checked_add(token_count).ok_or(PoolError::AmountsUnmatched)?;
+    }
+    else {
+
+        prepare_investor_token_account(&fp, investor, payer, investor_wallet, 
+            investor_token_account, pool_token, ata_program)?;
+
+        // thawed for the top-up of an existing holder, frozen again below
+        if fp.is_freezable {
+
+            set_token_account_frozen(&fp, false, investor_token_account, token_mint, 
+                pool_token_pda, token_program, program_id)?;
+        }
+    }
+
+    if fp.mint_mode == MintMode::Lazy {
+
+        // mint straight to the investor, the vault only 
+        // derives the token_pda which holds the mint authority 
+        let mint_ix = spl_token::instruction::mint_to_checked(
+            token_program.key,
+            token_mint.key,
+            token_dest_account.key,
+            &pda,
+            &[],
+            token_to_tx,
+            fp.decimals,
+        )?;
+
+        invoke_signed(&mint_ix,
+            &[
+                token_mint.clone(),
+                token_dest_account.clone(),
+                pool_token_pda.clone(),
+                token_program.clone(),
+            ],
+            &[&[addr[0], &[bump_seed]]],
+        )?;
+    }
+    else {
     
-    invoke_signed(&tf_to_inv_ix,
-        &[
-            pool_token_account.clone(),
-            investor_token_account.clone(),
-            pool_token_pda.clone(),
-            token_program.clone(),
-        ],
-        &[&[&addr[0][..], &[bump_seed]]],
-    )?;
+        let tf_to_inv_ix = spl_token::instruction::transfer_checked(
+            token_program.key,
+            pool_token_account.key,
+            token_mint.key,
+            token_dest_account.key,
+            &pda,
+            &[&pda],
+            token_to_tx,
+            fp.decimals,
+        )?;
+        
+        invoke_signed(&tf_to_inv_ix,
+            &[
+                pool_token_account.clone(),
+                token_mint.clone(),
+                token_dest_account.clone(),
+                pool_token_pda.clone(),
+                token_program.clone(),
+            ],
+            &[&[&addr[0][..], &[bump_seed]]],
+        )?;
+    }
+
+    // the vesting vault is the program's, only the investor's own account is frozen
+    if fp.is_freezable && vesting.is_none() {
+
+        set_token_account_frozen(&fp, true, investor_token_account, token_mint, 
+            pool_token_pda, token_program, program_id)?;
+    }
     
+    if let Some((mut v, vesting_account)) = vesting {
+
+        v.total_amount = v.total_amount.checked_add(token_to_tx).ok_or(PoolError::AmountsUnmatched)?;
+
+        VestingAccount::pack(v, &mut vesting_account.data.borrow_mut())?;
+    }
+
     // save the investor token account
-    i.token_account = *investor_token_account.key;
+    i.token_account = *token_dest_account.key;
+
+    record_investment(&mut i, amount_in_lamports, token_count, fund_pool_account, investment_account, 
+        payer, program_id)?;
 
     
     let inv = i.clone();
 
     let _ = Investor::pack(i, &mut investor_account.data.borrow_mut());
 
-    fp.rm_token_count = fp.rm_token_count - token_count;
-    let _ = fp.register_investor(inv);//
+    fp.rm_token_count = fp.rm_token_count.checked_sub(token_count).ok_or(PoolError::NotEnoughTokensLeft)?;
+    fp.set_investor_token_count(inv.investor, inv.address, inv.token_count)?;
     let _ = FundPool::pack(fp, &mut fund_pool_account.data.borrow_mut());
 
 
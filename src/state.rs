@@ -7,12 +7,15 @@ use solana_program::{
     program_error::ProgramError,
     program_pack::{IsInitialized,Pack,Sealed},
     clock::{Clock,UnixTimestamp},
+    keccak::{hashv, HASH_BYTES},
+    native_token::LAMPORTS_PER_SOL,
     sysvar::Sysvar, 
     //msg, 
 };
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use crate::{error::PoolError};
 use std::convert::{TryFrom};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 
 pub const USER_POOL_SIZE_LIMIT : usize = 50;
@@ -321,12 +324,163 @@ pub const FUND_POOL_INVESTOR_LIMIT : usize = 100;
 
 pub const FUND_POOL_WITHDRAWER_LIMIT : usize = 100;
 
+pub const MANAGER_SIGNER_LIMIT : usize = 5;
+
+pub const WHITELIST_TIER_LIMIT : usize = 4;
+
+pub const PRICE_TIER_LIMIT : usize = 5;
+
+const PRICE_TIER_LEN : usize = 16;
+
+const WHITELIST_TIER_LEN : usize = 16;
+
+// the seed of an investor's whitelist entry, 
+// followed by the fund pool and the investor's wallet
+pub const WHITELIST_SEED : &[u8] = b"whitelist";
+
+// the seeds of the mint and the token vault PDAs
+// of the pools with a program-owned mint 
+pub const MINT_SEED : &[u8] = b"mint";
+
+pub const VAULT_SEED : &[u8] = b"vault";
+
+
+#[derive(Clone, Copy, Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum MintMode {
+
+    // the mint is created by the client with 
+    // the manager as the mint authority 
+    Client = 0,
+
+    // the mint and the token vault are PDAs created by the program,
+    // which holds the mint authority
+    Program = 1,
+
+    // like Program, but nothing is pre-minted, the tokens are minted
+    // to the investors as they buy, so the supply is always the sold tokens
+    Lazy = 2,
+}
+
+
+// the fixed point scale of the exponential curve, parts per billion
+pub const CURVE_SCALE : u128 = 1_000_000_000;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum PricingMode {
+
+    // every token at the token_to_lamport_ratio
+    Fixed = 0,
+
+    // the price of the token sold s is ratio + curve_param * s
+    Linear = 1,
+
+    // the price of the token sold s is ratio * (1 + curve_param / CURVE_SCALE) ^ s
+    Exponential = 2,
+
+    // the price tiers by the tokens sold, a purchase is split across 
+    // the tiers it crosses, past the last tier it's the ratio
+    TieredBySold = 3,
+
+    // the price tiers by time, the threshold is the end of the 
+    // tier's window, past the last window it's the ratio
+    TieredByTime = 4,
+
+    // only bids, see the auction fields of the FundPool
+    DutchAuction = 5,
+
+    // the token price is curve_param in micro-USD (USD_SCALE), paid 
+    // in lamports at the SOL/USD price of the pool's price oracle
+    Oracle = 6,
+}
+
+
+// the micro-USD in a USD, the scale of the USD token price
+pub const USD_SCALE : u128 = 1_000_000;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PriceTier {
+
+    // the tokens sold or the unix timestamp the tier ends at
+    pub threshold : u64,
+
+    // lamports per token
+    pub price : u64,
+}
+
+
+// base ^ exp in the CURVE_SCALE fixed point, by squaring
+fn curve_pow(base : u128, exp : u128) -> Option<u128> {
+
+    let mut result = CURVE_SCALE;
+    let mut b = base;
+    let mut e = exp;
+
+    while e > 0 {
+
+        if e & 1 == 1 {
+
+            result = result.checked_mul(b)? / CURVE_SCALE;
+        }
+
+        e >>= 1;
+
+        if e > 0 {
+
+            b = b.checked_mul(b)? / CURVE_SCALE;
+        }
+    }
+
+    Some(result)
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WhitelistTier {
+
+    pub min_tokens : u64,
+
+    // zero means no limit
+    pub max_tokens : u64,
+}
+
+impl WhitelistTier {
+
+    pub fn is_valid(&self) -> bool {
+
+        self.max_tokens == 0 || self.min_tokens <= self.max_tokens
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum VestingSchedule {
+
+    // the tokens go straight to the investor
+    None = 0,
+
+    // everything unlocks at vesting_start + vesting_cliff
+    Cliff = 1,
+
+    // nothing before the cliff, then unlocks linearly 
+    // from vesting_start until vesting_start + vesting_duration
+    Linear = 2,
+}
+
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FundPool {
 
     pub is_initialized: bool,
 
+    // FUND_POOL_LAYOUT_VERSION, the pools of the original layout
+    // (LEGACY_FUND_POOL_LENGTH) are moved over by MigrateFundPool
+    pub layout_version : u8,
+
     pub manager : Pubkey, 
    
     pub address : Pubkey, 
@@ -354,6 +508,108 @@ pub struct FundPool {
     pub is_finalized : bool,
 
     pub icon : u16,
+
+    // the manager proposed by the current manager,
+    // who must accept before the pool is handed over
+    pub pending_manager : Pubkey,
+
+    // the optional M-of-N manager set, when the threshold
+    // is zero the single manager above signs alone
+    pub manager_threshold : u8,
+
+    manager_signers : Vec<Pubkey>,
+
+    // blocks new investments, refunds are still allowed
+    pub is_paused : bool,
+
+    pub decimals : u8,
+
+    pub mint_mode : MintMode,
+
+    // set by closing the raise, the unsold tokens are burnt
+    // and the sold_token_count is the final supply
+    pub is_raise_closed : bool,
+
+    pub sold_token_count : u64,
+
+    // when vesting, the purchased tokens are held in a per-investor
+    // vesting vault and released by ClaimVested
+    pub vesting_schedule : VestingSchedule,
+
+    pub vesting_start : UnixTimestamp,
+
+    pub vesting_cliff : i64,
+
+    pub vesting_duration : i64,
+
+    // the token_pda is the freeze authority of the mint, the investors'
+    // token accounts are frozen so the investors registry stays authoritative
+    pub is_freezable : bool,
+
+    // the limits of an investor's total token count,
+    // a zero max means no limit
+    pub min_tokens_per_investor : u64,
+
+    pub max_tokens_per_investor : u64,
+
+    // the whitelist tiers overriding the limits above,
+    // tier 1 is the first, tier 0 is the pool's limits
+    tiers : Vec<WhitelistTier>,
+
+    // the Merkle root of the invited investors, all zeros
+    // when anyone can invest
+    pub allowlist_root : [u8; HASH_BYTES],
+
+    // when set, only the wallets with a valid attestation 
+    // issued by this verifier can invest
+    pub required_verifier : Pubkey,
+
+    // the price of the next token is a function of the tokens sold,
+    // starting from the token_to_lamport_ratio
+    pub pricing_mode : PricingMode,
+
+    // Linear, the lamports added to the price per token sold.
+    // Exponential, the growth of the price per token sold 
+    // in parts per billion (CURVE_SCALE).
+    // Oracle, the token price in micro-USD
+    pub curve_param : u64,
+
+    // the price tiers of the tiered pricing modes, 
+    // by ascending threshold
+    price_tiers : Vec<PriceTier>,
+
+    // the Dutch auction, the price decays from the start price to the 
+    // floor price between the start and the end, the bids pay the 
+    // clearing price once the auction is settled
+    pub auction_start_price : u64,
+
+    pub auction_floor_price : u64,
+
+    pub auction_start : UnixTimestamp,
+
+    pub auction_end : UnixTimestamp,
+
+    pub clearing_price : u64,
+
+    pub is_auction_settled : bool,
+
+    // the lamports locked in the pool_pda by the bids not claimed yet
+    pub bid_lamports : u64,
+
+    // the share of the manager's fee going to the investor's referrer
+    pub referral_bps : u16,
+
+    // the SOL/USD price feed of the Oracle pricing mode, a price older 
+    // than the max age (in seconds) or less certain than the max 
+    // confidence (in basis points of the price) is refused, 
+    // the feed must be owned by the oracle program
+    pub price_oracle : Pubkey,
+
+    pub oracle_program : Pubkey,
+
+    pub oracle_max_age : i64,
+
+    pub oracle_max_conf_bps : u16,
        
     investors : Vec<FundPoolInvestor>,
     
@@ -368,7 +624,32 @@ impl Sealed for FundPool {}
 // 1 + 32 + 32 + 32 + 8 + 8 + 1 + ((32 + 32 + 8) * FUND_POOL_INVESTOR_LIMIT)
 // (32 + 32 + 8 + 8) * + FUND_POOL_WITHDRAWER_LIMIT
 // 84 + 2 // for the two lengths 
-const FUND_POOL_LENGTH : usize = 228 +
+// + 32 for the pending manager
+// + 1 + 1 + (32 * MANAGER_SIGNER_LIMIT) for the manager set
+// + 1 for is_paused
+// + 1 + 1 for the decimals and mint mode
+// + 1 + 8 for is_raise_closed and sold_token_count
+// + 1 + 8 + 8 + 8 for the vesting parameters
+// + 1 for is_freezable
+// + 8 + 8 + 1 + (16 * WHITELIST_TIER_LIMIT) for the investor limits and tiers
+// + 32 for the allowlist root
+// + 32 for the required verifier
+// + 1 + 8 for the pricing mode and curve param
+// + 1 + (16 * PRICE_TIER_LIMIT) for the price tiers
+// + 8 + 8 + 8 + 8 + 8 + 1 + 8 for the Dutch auction
+// + 2 for the referral bps
+// + 32 + 32 + 8 + 2 for the price oracle and its program
+// + 1 for the layout version
+// a changed layout bumps FUND_POOL_LAYOUT_VERSION and needs a migration (see the README)
+pub const FUND_POOL_LAYOUT_VERSION : u8 = 1;
+
+// the original layout, without a layout version
+pub const LEGACY_FUND_POOL_LENGTH : usize = 228 + 
+(FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT) + (FUND_POOL_INVESTOR_LEN * FUND_POOL_WITHDRAWER_LIMIT) + 2;
+
+const FUND_POOL_LENGTH : usize = 228 + 1 + PUBKEY_BYTES + 2 + (PUBKEY_BYTES * MANAGER_SIGNER_LIMIT) + 1 + 2 + 9 + 25 + 1 +
+17 + (WHITELIST_TIER_LEN * WHITELIST_TIER_LIMIT) + HASH_BYTES + PUBKEY_BYTES + 9 + 
+1 + (PRICE_TIER_LEN * PRICE_TIER_LIMIT) + 49 + 2 + PUBKEY_BYTES + PUBKEY_BYTES + 10 +
 (80 * FUND_POOL_INVESTOR_LIMIT) + (80 * FUND_POOL_WITHDRAWER_LIMIT)  + 2; 
 
 impl Pack for FundPool {
@@ -379,17 +660,26 @@ impl Pack for FundPool {
 
         let output = array_mut_ref![dst, 0, FUND_POOL_LENGTH];
        
-        let (is_initialized, manager, address, pool_pda, token_mint, 
+        let (is_initialized, layout_version, manager, address, pool_pda, token_mint, 
         token_account, token_pda, lamports, 
         token_count,rm_token_count, token_to_lamport_ratio, 
-        is_finalized,icon,ivs_len, 
+        is_finalized,icon,pending_manager,manager_threshold,
+        mgs_len,mg_data_flat,is_paused,decimals,mint_mode,is_raise_closed,sold_token_count,
+        vesting_schedule,vesting_start,vesting_cliff,vesting_duration,is_freezable,
+        min_tokens_per_investor,max_tokens_per_investor,tiers_len,tier_data_flat,allowlist_root,required_verifier,
+        pricing_mode,curve_param,pts_len,pt_data_flat,
+        auction_start_price,auction_floor_price,auction_start,auction_end,clearing_price,is_auction_settled,bid_lamports,referral_bps,
+        price_oracle,oracle_program,oracle_max_age,oracle_max_conf_bps,ivs_len, 
         wds_len,iv_data_flat,wd_data_flat) = 
-        mut_array_refs![ output,1,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,
-        PUBKEY_BYTES,8, 8,8,8,1,2,1,1, FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT, 
+        mut_array_refs![ output,1,1,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,
+        PUBKEY_BYTES,8, 8,8,8,1,2,PUBKEY_BYTES,1,1,PUBKEY_BYTES * MANAGER_SIGNER_LIMIT,1,1,1,1,8,
+        1,8,8,8,1,8,8,1,WHITELIST_TIER_LEN * WHITELIST_TIER_LIMIT,HASH_BYTES,PUBKEY_BYTES,1,8,
+        1,PRICE_TIER_LEN * PRICE_TIER_LIMIT,8,8,8,8,8,1,8,2,PUBKEY_BYTES,PUBKEY_BYTES,8,2,1,1, FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT, 
         FUND_POOL_INVESTOR_LEN * FUND_POOL_WITHDRAWER_LIMIT];
 
     
         pack_bool(self.is_initialized, is_initialized);
+        *layout_version = self.layout_version.to_le_bytes();
         manager.copy_from_slice(self.manager.as_ref());
         address.copy_from_slice(self.address.as_ref());
         pool_pda.copy_from_slice(self.pool_pda.as_ref());
@@ -403,12 +693,87 @@ impl Pack for FundPool {
         *token_to_lamport_ratio = self.token_to_lamport_ratio.to_le_bytes();
         *icon = self.icon.to_le_bytes();
         pack_bool(self.is_finalized, is_finalized);
+        pending_manager.copy_from_slice(self.pending_manager.as_ref());
+        *manager_threshold = self.manager_threshold.to_le_bytes();
+        *mgs_len = u8::try_from(self.manager_signers.len()).unwrap().to_le_bytes();
+
+        let mut offset = 0 ;
+
+        for mg in &self.manager_signers {
+
+            let mg_flat = array_mut_ref![mg_data_flat, offset, PUBKEY_BYTES];
+
+            mg_flat.copy_from_slice(mg.as_ref());
+
+            offset += PUBKEY_BYTES;
+        }
+
+        pack_bool(self.is_paused, is_paused);
+        *decimals = self.decimals.to_le_bytes();
+        *mint_mode = u8::from(self.mint_mode).to_le_bytes();
+        pack_bool(self.is_raise_closed, is_raise_closed);
+        *sold_token_count = self.sold_token_count.to_le_bytes();
+        *vesting_schedule = u8::from(self.vesting_schedule).to_le_bytes();
+        *vesting_start = self.vesting_start.to_le_bytes();
+        *vesting_cliff = self.vesting_cliff.to_le_bytes();
+        *vesting_duration = self.vesting_duration.to_le_bytes();
+        pack_bool(self.is_freezable, is_freezable);
+        *min_tokens_per_investor = self.min_tokens_per_investor.to_le_bytes();
+        *max_tokens_per_investor = self.max_tokens_per_investor.to_le_bytes();
+        *tiers_len = u8::try_from(self.tiers.len()).unwrap().to_le_bytes();
+
+        offset = 0 ;
+
+        for t in &self.tiers {
+
+            let t_flat = array_mut_ref![tier_data_flat, offset, WHITELIST_TIER_LEN];
+
+            let (min_tokens, max_tokens) = mut_array_refs![t_flat, 8, 8];
+
+            *min_tokens = t.min_tokens.to_le_bytes();
+            *max_tokens = t.max_tokens.to_le_bytes();
+
+            offset += WHITELIST_TIER_LEN;
+        }
+
+        allowlist_root.copy_from_slice(&self.allowlist_root);
+        required_verifier.copy_from_slice(self.required_verifier.as_ref());
+        *pricing_mode = u8::from(self.pricing_mode).to_le_bytes();
+        *curve_param = self.curve_param.to_le_bytes();
+        *pts_len = u8::try_from(self.price_tiers.len()).unwrap().to_le_bytes();
+
+        offset = 0 ;
+
+        for t in &self.price_tiers {
+
+            let t_flat = array_mut_ref![pt_data_flat, offset, PRICE_TIER_LEN];
+
+            let (threshold, price) = mut_array_refs![t_flat, 8, 8];
+
+            *threshold = t.threshold.to_le_bytes();
+            *price = t.price.to_le_bytes();
+
+            offset += PRICE_TIER_LEN;
+        }
+
+        *auction_start_price = self.auction_start_price.to_le_bytes();
+        *auction_floor_price = self.auction_floor_price.to_le_bytes();
+        *auction_start = self.auction_start.to_le_bytes();
+        *auction_end = self.auction_end.to_le_bytes();
+        *clearing_price = self.clearing_price.to_le_bytes();
+        pack_bool(self.is_auction_settled, is_auction_settled);
+        *bid_lamports = self.bid_lamports.to_le_bytes();
+        *referral_bps = self.referral_bps.to_le_bytes();
+        price_oracle.copy_from_slice(self.price_oracle.as_ref());
+        oracle_program.copy_from_slice(self.oracle_program.as_ref());
+        *oracle_max_age = self.oracle_max_age.to_le_bytes();
+        *oracle_max_conf_bps = self.oracle_max_conf_bps.to_le_bytes();
        
         *ivs_len = u8::try_from(self.investors.len()).unwrap().to_le_bytes();
         *wds_len = u8::try_from(self.withdrawers.len()).unwrap().to_le_bytes();
 
 
-        let mut offset = 0 ;
+        offset = 0 ;
 
         for iv in &self.investors {
 
@@ -453,16 +818,33 @@ impl Pack for FundPool {
        
         let input = array_ref![src, 0, FUND_POOL_LENGTH];
        
-        let (is_initialized,manager, address,pool_pda, token_mint, token_account, 
+        let (is_initialized,layout_version,manager, address,pool_pda, token_mint, token_account, 
             token_pda,  lamports, token_count,rm_token_count, token_to_lamport_ratio,
-            is_finalized, icon, invs_len, wds_len, invs_flat,wds_flat) =
+            is_finalized, icon, pending_manager, manager_threshold, mgs_len, mgs_flat,
+            is_paused, decimals, mint_mode, is_raise_closed, sold_token_count,
+            vesting_schedule, vesting_start, vesting_cliff, vesting_duration, is_freezable,
+            min_tokens_per_investor, max_tokens_per_investor, tiers_len, tiers_flat, allowlist_root,
+            required_verifier, pricing_mode, curve_param, pts_len, pts_flat, 
+            auction_start_price, auction_floor_price, auction_start, auction_end, 
+            clearing_price, is_auction_settled, bid_lamports, referral_bps, 
+            price_oracle, oracle_program, oracle_max_age, oracle_max_conf_bps, invs_len, wds_len, invs_flat,wds_flat) =
 
         array_refs![input, 
-        1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,
-        8, 8, 8,8, 1, 2, 1,1, (FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT), 
+        1, 1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,
+        8, 8, 8,8, 1, 2, PUBKEY_BYTES, 1, 1, PUBKEY_BYTES * MANAGER_SIGNER_LIMIT, 1, 1, 1, 1, 8, 
+        1, 8, 8, 8, 1, 8, 8, 1, WHITELIST_TIER_LEN * WHITELIST_TIER_LIMIT, HASH_BYTES, PUBKEY_BYTES, 1, 8, 
+        1, PRICE_TIER_LEN * PRICE_TIER_LIMIT, 8, 8, 8, 8, 8, 1, 8, 2, PUBKEY_BYTES, PUBKEY_BYTES, 8, 2, 1,1, (FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT), 
         (FUND_POOL_INVESTOR_LEN * FUND_POOL_WITHDRAWER_LIMIT)];
 
         let is_init = unpack_bool(is_initialized).unwrap();
+        let layout_version = u8::from_le_bytes(*layout_version);
+
+        // an initialized pool of another layout must be migrated first
+        if is_init && layout_version != FUND_POOL_LAYOUT_VERSION {
+
+            return Err(ProgramError::InvalidAccountData);
+        }
+
         let is_final = unpack_bool(is_finalized).unwrap();
         let mgr = Pubkey::new_from_array(*manager);
         let addr = Pubkey::new_from_array(*address);
@@ -477,12 +859,66 @@ impl Pack for FundPool {
         let rm_tkc = u64::from_le_bytes(*rm_token_count);
         let tk_ratio = u64::from_le_bytes(*token_to_lamport_ratio);
         let ic = u16::from_le_bytes(*icon);
+        let pending_mgr = Pubkey::new_from_array(*pending_manager);
+        let mg_threshold = u8::from_le_bytes(*manager_threshold);
+
+        let mgs_len = u8::from_le_bytes(*mgs_len);
+        let mut mgs = Vec::with_capacity(mgs_len as usize);
+
+        let mut offset = 0 ;
+
+        for _ in 0..mgs_len {
+
+            let pk = array_ref![mgs_flat, offset, PUBKEY_BYTES];
+
+            mgs.push(Pubkey::new_from_array(*pk));
+
+            offset += PUBKEY_BYTES;
+        }
+
+        let tiers_len = u8::from_le_bytes(*tiers_len);
+        let mut tiers = Vec::with_capacity(tiers_len as usize);
+
+        offset = 0 ;
+
+        for _ in 0..tiers_len {
+
+            let t_flat = array_ref![tiers_flat, offset, WHITELIST_TIER_LEN];
+
+            let (min_tokens, max_tokens) = array_refs![t_flat, 8, 8];
+
+            tiers.push(WhitelistTier {
+                min_tokens : u64::from_le_bytes(*min_tokens),
+                max_tokens : u64::from_le_bytes(*max_tokens),
+            });
+
+            offset += WHITELIST_TIER_LEN;
+        }
+
+        let pts_len = u8::from_le_bytes(*pts_len);
+        let mut price_tiers = Vec::with_capacity(pts_len as usize);
+
+        offset = 0 ;
+
+        for _ in 0..pts_len {
+
+            let t_flat = array_ref![pts_flat, offset, PRICE_TIER_LEN];
+
+            let (threshold, price) = array_refs![t_flat, 8, 8];
+
+            price_tiers.push(PriceTier {
+                threshold : u64::from_le_bytes(*threshold),
+                price : u64::from_le_bytes(*price),
+            });
+
+            offset += PRICE_TIER_LEN;
+        }
     
         
         let invs_len = u8::from_le_bytes(*invs_len);
         let mut invs =  Vec::with_capacity(invs_len as usize);
 
-        let mut offset = 0 ;
+        offset = 0 ;
 
         for _ in 0..invs_len {
 
@@ -533,6 +969,7 @@ impl Pack for FundPool {
 
         Ok (Self {
             is_initialized : is_init, 
+            layout_version,
             manager : mgr,
             address : addr,
             pool_pda : pda, 
@@ -545,6 +982,42 @@ impl Pack for FundPool {
             token_to_lamport_ratio : tk_ratio, 
             is_finalized : is_final,
             icon : ic, 
+            pending_manager : pending_mgr,
+            manager_threshold : mg_threshold,
+            manager_signers : mgs,
+            is_paused : unpack_bool(is_paused)?,
+            decimals : u8::from_le_bytes(*decimals),
+            mint_mode : MintMode::try_from_primitive(u8::from_le_bytes(*mint_mode))
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+            is_raise_closed : unpack_bool(is_raise_closed)?,
+            sold_token_count : u64::from_le_bytes(*sold_token_count),
+            vesting_schedule : VestingSchedule::try_from_primitive(u8::from_le_bytes(*vesting_schedule))
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+            vesting_start : i64::from_le_bytes(*vesting_start),
+            vesting_cliff : i64::from_le_bytes(*vesting_cliff),
+            vesting_duration : i64::from_le_bytes(*vesting_duration),
+            is_freezable : unpack_bool(is_freezable)?,
+            min_tokens_per_investor : u64::from_le_bytes(*min_tokens_per_investor),
+            max_tokens_per_investor : u64::from_le_bytes(*max_tokens_per_investor),
+            tiers,
+            allowlist_root : *allowlist_root,
+            required_verifier : Pubkey::new_from_array(*required_verifier),
+            pricing_mode : PricingMode::try_from_primitive(u8::from_le_bytes(*pricing_mode))
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+            curve_param : u64::from_le_bytes(*curve_param),
+            price_tiers,
+            auction_start_price : u64::from_le_bytes(*auction_start_price),
+            auction_floor_price : u64::from_le_bytes(*auction_floor_price),
+            auction_start : i64::from_le_bytes(*auction_start),
+            auction_end : i64::from_le_bytes(*auction_end),
+            clearing_price : u64::from_le_bytes(*clearing_price),
+            is_auction_settled : unpack_bool(is_auction_settled)?,
+            bid_lamports : u64::from_le_bytes(*bid_lamports),
+            referral_bps : u16::from_le_bytes(*referral_bps),
+            price_oracle : Pubkey::new_from_array(*price_oracle),
+            oracle_program : Pubkey::new_from_array(*oracle_program),
+            oracle_max_age : i64::from_le_bytes(*oracle_max_age),
+            oracle_max_conf_bps : u16::from_le_bytes(*oracle_max_conf_bps),
             investors : invs,
             withdrawers : wds, 
         })
@@ -552,6 +1025,87 @@ impl Pack for FundPool {
     }
 }
 
+
+impl FundPool {
+
+    /*
+    Reads a pool of the original layout (LEGACY_FUND_POOL_LENGTH) into the 
+    current one, the fields added since take their defaults. The original 
+    layout kept the withdrawers' running offset from the investors, so does this
+    */
+    pub fn unpack_legacy(src : &[u8]) -> Result<Self, ProgramError> {
+
+        if src.len() != LEGACY_FUND_POOL_LENGTH {
+
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let input = array_ref![src, 0, LEGACY_FUND_POOL_LENGTH];
+
+        let (is_initialized, manager, address, pool_pda, token_mint, token_account, 
+            token_pda, lamports, token_count, rm_token_count, token_to_lamport_ratio,
+            is_finalized, icon, invs_len, wds_len, invs_flat, wds_flat) =
+
+        array_refs![input, 
+        1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES,
+        8, 8, 8, 8, 1, 2, 1, 1, FUND_POOL_INVESTOR_LEN * FUND_POOL_INVESTOR_LIMIT, 
+        FUND_POOL_INVESTOR_LEN * FUND_POOL_WITHDRAWER_LIMIT];
+
+        let mut fp = FundPool::new(unpack_bool(is_initialized)?);
+        fp.manager = Pubkey::new_from_array(*manager);
+        fp.address = Pubkey::new_from_array(*address);
+        fp.pool_pda = Pubkey::new_from_array(*pool_pda);
+        fp.token_mint = Pubkey::new_from_array(*token_mint);
+        fp.token_account = Pubkey::new_from_array(*token_account);
+        fp.token_pda = Pubkey::new_from_array(*token_pda);
+        fp.fee_in_lamports = u64::from_le_bytes(*lamports);
+        fp.token_count = u64::from_le_bytes(*token_count);
+        fp.rm_token_count = u64::from_le_bytes(*rm_token_count);
+        fp.token_to_lamport_ratio = u64::from_le_bytes(*token_to_lamport_ratio);
+        fp.is_finalized = unpack_bool(is_finalized)?;
+        fp.icon = u16::from_le_bytes(*icon);
+
+        let invs_len = u8::from_le_bytes(*invs_len) as usize;
+        let wds_len = u8::from_le_bytes(*wds_len) as usize;
+
+        if invs_len > FUND_POOL_INVESTOR_LIMIT || invs_len + wds_len > FUND_POOL_WITHDRAWER_LIMIT {
+
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut offset = 0;
+
+        for _ in 0..invs_len {
+
+            fp.investors.push(unpack_legacy_investor_entry(array_ref![invs_flat, offset, FUND_POOL_INVESTOR_LEN]));
+
+            offset += FUND_POOL_INVESTOR_LEN;
+        }
+
+        for _ in 0..wds_len {
+
+            fp.withdrawers.push(unpack_legacy_investor_entry(array_ref![wds_flat, offset, FUND_POOL_INVESTOR_LEN]));
+
+            offset += FUND_POOL_INVESTOR_LEN;
+        }
+
+        Ok(fp)
+    }
+}
+
+
+fn unpack_legacy_investor_entry(src : &[u8; FUND_POOL_INVESTOR_LEN]) -> FundPoolInvestor {
+
+    let (address, investor, token_count, date) = array_refs![src, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8];
+
+    FundPoolInvestor {
+        investor : Pubkey::new_from_array(*investor),
+        address : Pubkey::new_from_array(*address),
+        token_count : u64::from_le_bytes(*token_count),
+        date : i64::from_le_bytes(*date),
+    }
+}
+
 impl IsInitialized for FundPool {
     fn is_initialized(&self) -> bool {
         
@@ -567,6 +1121,7 @@ impl FundPool {
         FundPool{
 
             is_initialized : is_initialized,
+            layout_version : FUND_POOL_LAYOUT_VERSION,
             manager : Pubkey::default(),
             address : Pubkey::default(),
             pool_pda : Pubkey::default(),
@@ -579,6 +1134,39 @@ impl FundPool {
             token_to_lamport_ratio : 0, 
             is_finalized : false,
             icon : 0,
+            pending_manager : Pubkey::default(),
+            manager_threshold : 0,
+            manager_signers : Vec::with_capacity(MANAGER_SIGNER_LIMIT),
+            is_paused : false,
+            decimals : 0,
+            mint_mode : MintMode::Client,
+            is_raise_closed : false,
+            sold_token_count : 0,
+            vesting_schedule : VestingSchedule::None,
+            vesting_start : 0,
+            vesting_cliff : 0,
+            vesting_duration : 0,
+            is_freezable : false,
+            min_tokens_per_investor : 0,
+            max_tokens_per_investor : 0,
+            tiers : Vec::with_capacity(WHITELIST_TIER_LIMIT),
+            allowlist_root : [0; HASH_BYTES],
+            required_verifier : Pubkey::default(),
+            pricing_mode : PricingMode::Fixed,
+            curve_param : 0,
+            price_tiers : Vec::with_capacity(PRICE_TIER_LIMIT),
+            auction_start_price : 0,
+            auction_floor_price : 0,
+            auction_start : 0,
+            auction_end : 0,
+            clearing_price : 0,
+            is_auction_settled : false,
+            bid_lamports : 0,
+            referral_bps : 0,
+            price_oracle : Pubkey::default(),
+            oracle_program : Pubkey::default(),
+            oracle_max_age : 0,
+            oracle_max_conf_bps : 0,
             investors : Vec::with_capacity(FUND_POOL_INVESTOR_LIMIT),
             withdrawers : Vec::with_capacity(FUND_POOL_WITHDRAWER_LIMIT),
             
@@ -633,144 +1221,1651 @@ impl FundPool {
     }
 
 
-    pub fn investor_count(&self) -> usize {
+    // the number of base units per whole pool token
+    pub fn token_multiplier(&self) -> Option<u64> {
 
-        self.investors.len() 
+        10u64.checked_pow(self.decimals as u32)
     }
 
-}
 
+    // the token_count in base units, None on overflow
+    pub fn token_amount(&self, token_count : u64) -> Option<u64> {
 
-impl FundPool {
+        self.token_multiplier()?.checked_mul(token_count)
+    }
 
-    pub fn register_withdrawer(&mut self, withdrawer : FundPoolInvestor) -> bool  {
 
-        if self.withdrawers.len() < FUND_POOL_WITHDRAWER_LIMIT  {
+    pub fn is_vesting(&self) -> bool {
 
-            if !self.withdrawers.contains(&withdrawer){
+        self.vesting_schedule != VestingSchedule::None
+    }
 
 
-                let mut wd = withdrawer;
+    // the part of the total that has unlocked at the time now
+    pub fn vested_amount(&self, total : u64, now : UnixTimestamp) -> u64 {
 
-                wd.date = Clock::get().unwrap().unix_timestamp;
-              //  msg!("Current date time:: {}", wd.date);
+        let elapsed = now.saturating_sub(self.vesting_start);
 
-                self.withdrawers.push(wd);
+        match self.vesting_schedule {
 
-                return true; 
-                
-            }
+            VestingSchedule::None => total,
 
-        }
+            VestingSchedule::Cliff => if elapsed >= self.vesting_cliff { total } else { 0 },
 
-        return false; 
-       
+            VestingSchedule::Linear => {
+
+                if elapsed < self.vesting_cliff {
+                    0
+                }
+                else if elapsed >= self.vesting_duration {
+                    total
+                }
+                else {
+                    ((total as u128) * (elapsed as u128) / (self.vesting_duration as u128)) as u64
+                }
+            },
+        }
     }
 
 
-    pub fn withdrawer_count(&self) -> usize {
+    pub fn investor_count(&self) -> usize {
 
-        self.withdrawers.len() 
+        self.investors.len() 
     }
 
- 
-}
 
+    /*
+    Sets the token count of the investor record in the registry, 
+    a zero count removes it and an unknown record is registered
+    */
+    pub fn set_investor_token_count(&mut self, investor : Pubkey, address : Pubkey, 
+        token_count : u64) -> Result<bool, PoolError> {
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Investor {
+        let idx = self.investors.iter().position(|r| r.address == address);
 
-    pub investor : Pubkey, 
+        match idx {
 
-    pub pool_address : Pubkey, 
+            Some(i) => {
 
-    pub address : Pubkey,
+                if token_count == 0 {
 
-    pub amount : u64, 
+                    self.investors.remove(i);
+                }
+                else {
 
-    pub token_account : Pubkey,
+                    self.investors[i].token_count = token_count;
+                }
 
-    pub token_count : u64,
-  
-    pub date : UnixTimestamp, 
+                Ok(true)
+            },
 
-}
+            None => {
 
-impl Investor {
+                if token_count == 0 {
 
-    pub fn new() -> Self {
+                    return Ok(false);
+                }
 
-        Investor {
-            investor : Pubkey::default(),
-            pool_address : Pubkey::default(),
-            address : Pubkey::default(),
-            amount : 0,
-            token_account : Pubkey::default(),
-            token_count : 0,
-            date : Clock::get().unwrap().unix_timestamp, 
+                self.register_fund_pool_investor(FundPoolInvestor {
+                    investor,
+                    address,
+                    token_count,
+                    date : 0,
+                })
+            }
         }
     }
-}
 
-impl Sealed for Investor {}
 
+    pub fn remove_investor(&mut self, address : Pubkey) -> Result<FundPoolInvestor, PoolError> {
 
-const INVESTOR_DATA_SIZE : usize = PUBKEY_BYTES + PUBKEY_BYTES + 
-PUBKEY_BYTES + 8 + PUBKEY_BYTES + 8 + 8;
+        let idx = self.investors.iter().position(|r| r.address == address);
 
-impl Pack for Investor {
+        match idx {
 
-    const LEN: usize = INVESTOR_DATA_SIZE;
+            Some(i) => Ok(self.investors.remove(i)),
 
-    fn pack_into_slice(&self, dst: &mut [u8]) {
+            None => Err(PoolError::InvestorNotFound),
+        }
+    }
 
-        let output = array_mut_ref![dst, 0, INVESTOR_DATA_SIZE];
-       
-        let (investor,pool_address, address, amount,token_account,token_count, date) = 
-        mut_array_refs![ output,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,8,PUBKEY_BYTES, 8, 8];
+}
+
+
+impl FundPool {
+
+    pub fn set_manager_signers(&mut self, threshold : u8, signers : Vec<Pubkey>) -> Result<bool, PoolError> {
+
+        if signers.len() > MANAGER_SIGNER_LIMIT || (threshold as usize) > signers.len() {
+
+            return Err(PoolError::InvalidManagerSigners);
+        }
+
+        if threshold == 0 && !signers.is_empty() {
+
+            return Err(PoolError::InvalidManagerSigners);
+        }
+
+        for (i, s) in signers.iter().enumerate() {
+
+            if signers[..i].contains(s) {
+
+                return Err(PoolError::InvalidManagerSigners);
+            }
+        }
+
+        self.manager_threshold = threshold;
+        self.manager_signers = signers;
+
+        Ok(true)
+    }
+
+
+    pub fn set_investor_limits(&mut self, min_tokens : u64, max_tokens : u64, 
+        tiers : Vec<WhitelistTier>) -> Result<bool, PoolError> {
+
+        if tiers.len() > WHITELIST_TIER_LIMIT {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        let pool_tier = WhitelistTier { min_tokens, max_tokens };
+
+        if !pool_tier.is_valid() || tiers.iter().any(|t| !t.is_valid()) {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        self.min_tokens_per_investor = min_tokens;
+        self.max_tokens_per_investor = max_tokens;
+        self.tiers = tiers;
+
+        Ok(true)
+    }
+
+
+    pub fn tiers(&self) -> Vec<WhitelistTier> {
+
+        self.tiers.clone()
+    }
+
+
+    pub fn has_tiers(&self) -> bool {
+
+        !self.tiers.is_empty()
+    }
+
+
+    /*
+    Checks an investor's total token count against the 
+    limits of the tier, tier 0 is the pool's own limits
+    */
+    pub fn check_investor_token_count(&self, token_count : u64, tier : u8) -> Result<bool, PoolError> {
+
+        let limits = if tier == 0 {
+
+            WhitelistTier {
+                min_tokens : self.min_tokens_per_investor,
+                max_tokens : self.max_tokens_per_investor,
+            }
+        }
+        else {
+
+            *self.tiers.get((tier - 1) as usize).ok_or(PoolError::InvalidWhitelistTier)?
+        };
+
+        if token_count < limits.min_tokens {
+
+            return Err(PoolError::BelowMinInvestment);
+        }
+
+        if limits.max_tokens > 0 && token_count > limits.max_tokens {
+
+            return Err(PoolError::AboveMaxInvestment);
+        }
+
+        Ok(true)
+    }
+
+
+    pub fn sold_count(&self) -> u64 {
+
+        self.token_count.saturating_sub(self.rm_token_count)
+    }
+
+
+    /*
+    The lamports of buying the token count next, the sum of the 
+    price of each token along the curve from the tokens sold
+    */
+    pub fn purchase_cost(&self, token_count : u64, now : UnixTimestamp) -> Option<u64> {
+
+        let ratio = self.token_to_lamport_ratio as u128;
+        let n = token_count as u128;
+        let s = self.sold_count() as u128;
+
+        let cost = match self.pricing_mode {
+
+            PricingMode::Fixed => ratio.checked_mul(n)?,
+
+            PricingMode::Linear => {
+
+                // n * ratio + slope * (s * n + n * (n - 1) / 2)
+                let steps = s.checked_mul(n)?.checked_add(n * n.saturating_sub(1) / 2)?;
+
+                ratio.checked_mul(n)?.checked_add((self.curve_param as u128).checked_mul(steps)?)?
+            },
+
+            PricingMode::Exponential => {
+
+                if self.curve_param == 0 {
+
+                    return ratio.checked_mul(n).and_then(|c| u64::try_from(c).ok());
+                }
+
+                // the geometric sum ratio * (g^(s + n) - g^s) / (g - 1)
+                let g = CURVE_SCALE + self.curve_param as u128;
+                let from = curve_pow(g, s)?;
+                let to = curve_pow(g, s.checked_add(n)?)?;
+
+                ratio.checked_mul(to - from)? / (self.curve_param as u128)
+            },
+
+            PricingMode::TieredBySold => {
+
+                let mut cost : u128 = 0;
+                let mut sold = s;
+                let mut left = n;
+
+                for t in &self.price_tiers {
+
+                    let end = t.threshold as u128;
+
+                    if left == 0 || sold >= end {
+
+                        continue;
+                    }
+
+                    let count = left.min(end - sold);
+
+                    cost = cost.checked_add((t.price as u128).checked_mul(count)?)?;
+                    sold += count;
+                    left -= count;
+                }
+
+                cost.checked_add(ratio.checked_mul(left)?)?
+            },
+
+            PricingMode::TieredByTime => {
+
+                let price = self.price_tiers.iter()
+                .find(|t| (now.max(0) as u64) < t.threshold)
+                .map(|t| t.price as u128)
+                .unwrap_or(ratio);
+
+                price.checked_mul(n)?
+            },
+
+            PricingMode::DutchAuction | PricingMode::Oracle => return None,
+        };
+
+        u64::try_from(cost).ok()
+    }
+
+
+    /*
+    The lamports of buying the token count of an Oracle priced pool,
+    the feed is checked by the caller
+    */
+    pub fn oracle_purchase_cost(&self, token_count : u64, feed : &PriceFeed) -> Option<u64> {
+
+        let usd = (self.curve_param as u128).checked_mul(token_count as u128)?;
+
+        feed.usd_to_lamports(usd)
+    }
+
+
+    pub fn set_oracle_pricing(&mut self, oracle : Pubkey, oracle_program : Pubkey, usd_price : u64, 
+        max_age : i64, max_conf_bps : u16) -> Result<bool, PoolError> {
+
+        if oracle == Pubkey::default() || oracle_program == Pubkey::default() || usd_price == 0 || max_age <= 0 || max_conf_bps > MAX_BASIS_POINTS {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        self.pricing_mode = PricingMode::Oracle;
+        self.curve_param = usd_price;
+        self.price_oracle = oracle;
+        self.oracle_program = oracle_program;
+        self.oracle_max_age = max_age;
+        self.oracle_max_conf_bps = max_conf_bps;
+
+        Ok(true)
+    }
+
+
+    // the referrer's share of the manager's fee
+    pub fn referral_reward(&self) -> Option<u64> {
+
+        let reward = (self.fee_in_lamports as u128).checked_mul(self.referral_bps as u128)? 
+        / (MAX_BASIS_POINTS as u128);
+
+        u64::try_from(reward).ok()
+    }
+
+
+    /*
+    The auction price at the time, from the start price 
+    decaying linearly to the floor price at the end
+    */
+    pub fn auction_price(&self, now : UnixTimestamp) -> u64 {
+
+        if now <= self.auction_start {
+
+            return self.auction_start_price;
+        }
+
+        if now >= self.auction_end {
+
+            return self.auction_floor_price;
+        }
+
+        let drop = (self.auction_start_price - self.auction_floor_price) as u128 * 
+        (now - self.auction_start) as u128 / (self.auction_end - self.auction_start) as u128;
+
+        self.auction_start_price - drop as u64
+    }
+
+
+    pub fn set_auction(&mut self, start_price : u64, floor_price : u64, 
+        start : UnixTimestamp, end : UnixTimestamp) -> Result<bool, PoolError> {
+
+        if floor_price == 0 || start_price < floor_price || start >= end {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        self.pricing_mode = PricingMode::DutchAuction;
+        self.auction_start_price = start_price;
+        self.auction_floor_price = floor_price;
+        self.auction_start = start;
+        self.auction_end = end;
+        self.clearing_price = 0;
+        self.is_auction_settled = false;
+
+        Ok(true)
+    }
+
+
+    pub fn set_price_tiers(&mut self, tiers : Vec<PriceTier>) -> Result<bool, PoolError> {
+
+        if tiers.len() > PRICE_TIER_LIMIT || tiers.iter().any(|t| t.price == 0) {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        if tiers.windows(2).any(|w| w[0].threshold >= w[1].threshold) {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        self.price_tiers = tiers;
+
+        Ok(true)
+    }
+
+
+    pub fn price_tiers(&self) -> Vec<PriceTier> {
+
+        self.price_tiers.clone()
+    }
+
+
+    pub fn has_allowlist(&self) -> bool {
+
+        self.allowlist_root != [0; HASH_BYTES]
+    }
+
+
+    /*
+    Verifies the Merkle proof of the leaf keccak(wallet, allocation) 
+    against the allowlist root, the pairs are hashed in sorted order
+    */
+    pub fn is_allowlisted(&self, wallet : &Pubkey, allocation : u64, proof : &[[u8; HASH_BYTES]]) -> bool {
+
+        let mut computed = hashv(&[wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes();
+
+        for p in proof {
+
+            computed = if computed <= *p {
+                hashv(&[&computed, p]).to_bytes()
+            }
+            else {
+                hashv(&[p, &computed]).to_bytes()
+            };
+        }
+
+        computed == self.allowlist_root
+    }
+
+
+    pub fn manager_signers(&self) -> Vec<Pubkey> {
+
+        self.manager_signers.clone()
+    }
+
+
+    pub fn is_multisig(&self) -> bool {
+
+        self.manager_threshold > 0
+    }
+
+}
+
+
+impl FundPool {
+
+    pub fn register_withdrawer(&mut self, withdrawer : FundPoolInvestor) -> bool  {
+
+        if self.withdrawers.len() < FUND_POOL_WITHDRAWER_LIMIT  {
+
+            if !self.withdrawers.contains(&withdrawer){
+
+
+                let mut wd = withdrawer;
+
+                wd.date = Clock::get().unwrap().unix_timestamp;
+              //  msg!("Current date time:: {}", wd.date);
+
+                self.withdrawers.push(wd);
+
+                return true; 
+                
+            }
+
+        }
+
+        return false; 
+       
+    }
+
+
+    pub fn withdrawer_count(&self) -> usize {
+
+        self.withdrawers.len() 
+    }
+
+ 
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Investor {
+
+    pub investor : Pubkey, 
+
+    pub pool_address : Pubkey, 
+
+    pub address : Pubkey,
+
+    pub amount : u64, 
+
+    pub token_account : Pubkey,
+
+    pub token_count : u64,
+  
+    pub date : UnixTimestamp, 
+
+    // the number of investments, each has its Investment history account
+    pub investment_count : u64,
+
+    // the part of the token count escrowed in open sell orders
+    pub escrowed_token_count : u64,
+
+    // the part of the token count still in the vesting vault
+    pub vesting_token_count : u64,
+
+}
+
+impl Investor {
+
+    pub fn new() -> Self {
+
+        Investor {
+            investor : Pubkey::default(),
+            pool_address : Pubkey::default(),
+            address : Pubkey::default(),
+            amount : 0,
+            token_account : Pubkey::default(),
+            token_count : 0,
+            date : Clock::get().unwrap().unix_timestamp, 
+            investment_count : 0,
+            escrowed_token_count : 0,
+            vesting_token_count : 0,
+        }
+    }
+}
+
+impl Investor {
+
+    /*
+    The part of the amount paid into the pool that goes with
+    the token count moved out of this position
+    */
+    pub fn amount_of(&self, token_count : u64) -> Option<u64> {
+
+        if token_count > self.token_count {
+
+            return None;
+        }
+
+        if token_count == self.token_count {
+
+            return Some(self.amount);
+        }
+
+        let amount = (self.amount as u128).checked_mul(token_count as u128)? 
+        / (self.token_count as u128);
+
+        u64::try_from(amount).ok()
+    }
+
+
+    // the token count held by the wallet and not escrowed in sell orders
+    pub fn available_token_count(&self) -> u64 {
+
+        self.token_count.saturating_sub(self.escrowed_token_count)
+        .saturating_sub(self.vesting_token_count)
+    }
+}
+
+impl Sealed for Investor {}
+
+
+const INVESTOR_DATA_SIZE : usize = PUBKEY_BYTES + PUBKEY_BYTES + 
+PUBKEY_BYTES + 8 + PUBKEY_BYTES + 8 + 8 + 8 + 8 + 8;
+
+// the original layout, the fields added since are appended after it
+pub const LEGACY_INVESTOR_DATA_SIZE : usize = PUBKEY_BYTES + PUBKEY_BYTES + 
+PUBKEY_BYTES + 8 + PUBKEY_BYTES + 8 + 8;
+
+impl Investor {
+
+    // a record of the original layout, the fields added since are zero
+    pub fn unpack_legacy(src : &[u8]) -> Result<Self, ProgramError> {
+
+        if src.len() != LEGACY_INVESTOR_DATA_SIZE {
+
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut data = [0; INVESTOR_DATA_SIZE];
+        data[..LEGACY_INVESTOR_DATA_SIZE].copy_from_slice(src);
+
+        Investor::unpack_from_slice(&data)
+    }
+}
+
+impl Pack for Investor {
+
+    const LEN: usize = INVESTOR_DATA_SIZE;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, INVESTOR_DATA_SIZE];
+       
+        let (investor,pool_address, address, amount,token_account,token_count, date, investment_count,
+        escrowed_token_count, vesting_token_count) = 
+        mut_array_refs![ output,PUBKEY_BYTES,PUBKEY_BYTES,PUBKEY_BYTES,8,PUBKEY_BYTES, 8, 8, 8, 8, 8];
+
+
+        investor.copy_from_slice(self.investor.as_ref());
+        pool_address.copy_from_slice(self.pool_address.as_ref());
+        address.copy_from_slice(self.address.as_ref());
+        
+        *token_count = self.token_count.to_le_bytes();
+        *amount = self.amount.to_le_bytes();
+        token_account.copy_from_slice(self.token_account.as_ref());
+        *date = self.date.to_le_bytes();
+        *investment_count = self.investment_count.to_le_bytes();
+        *escrowed_token_count = self.escrowed_token_count.to_le_bytes();
+        *vesting_token_count = self.vesting_token_count.to_le_bytes();
+
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+   
+        let input = array_ref![src, 0, INVESTOR_DATA_SIZE];
+       
+        let (investor,pool_address, address, amount,token_account,token_count, date, investment_count,
+        escrowed_token_count, vesting_token_count)  =
+
+        array_refs![input, PUBKEY_BYTES, PUBKEY_BYTES,
+        PUBKEY_BYTES, 8, PUBKEY_BYTES, 8, 8, 8, 8, 8 ];
+
+        let investor = Pubkey::new_from_array(*investor);
+        let pool_address = Pubkey::new_from_array(*pool_address);
+        let token_account = Pubkey::new_from_array(*token_account);
+        let address = Pubkey::new_from_array(*address);
+        let amount = u64::from_le_bytes(*amount);
+        let token_count = u64::from_le_bytes(*token_count);
+        let date = i64::from_le_bytes(*date);
+
+        Ok( Investor{
+            investor : investor,
+            address : address,
+            pool_address : pool_address,
+            token_account : token_account,
+            amount :amount, 
+            token_count : token_count,
+            date : date, 
+            investment_count : u64::from_le_bytes(*investment_count),
+            escrowed_token_count : u64::from_le_bytes(*escrowed_token_count),
+            vesting_token_count : u64::from_le_bytes(*vesting_token_count),
+        })
+    }
+}
+
+
+// the seed of an investment history account, followed by
+// the investor account and the investment's index
+pub const INVESTMENT_SEED : &[u8] = b"investment";
+
+
+/*
+A single investment of an investor, the Investor record 
+holds the accumulated amount and token count
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Investment {
+
+    pub is_initialized : bool,
+
+    pub investor_account : Pubkey,
+
+    pub fund_pool : Pubkey,
+
+    pub index : u64,
+
+    pub amount : u64,
+
+    pub token_count : u64,
+
+    pub date : UnixTimestamp,
+}
+
+impl Investment {
+
+    pub fn new() -> Self {
+
+        Investment {
+            is_initialized : false,
+            investor_account : Pubkey::default(),
+            fund_pool : Pubkey::default(),
+            index : 0,
+            amount : 0,
+            token_count : 0,
+            date : 0,
+        }
+    }
+}
+
+impl Default for Investment {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for Investment {}
+
+impl IsInitialized for Investment {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const INVESTMENT_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 8 + 8 + 8 + 8;
+
+impl Pack for Investment {
+
+    const LEN: usize = INVESTMENT_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, INVESTMENT_LENGTH];
+
+        let (is_initialized, investor_account, fund_pool, index, amount, token_count, date) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        investor_account.copy_from_slice(self.investor_account.as_ref());
+        fund_pool.copy_from_slice(self.fund_pool.as_ref());
+        *index = self.index.to_le_bytes();
+        *amount = self.amount.to_le_bytes();
+        *token_count = self.token_count.to_le_bytes();
+        *date = self.date.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, INVESTMENT_LENGTH];
+
+        let (is_initialized, investor_account, fund_pool, index, amount, token_count, date) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8, 8, 8];
+
+        Ok(Investment {
+            is_initialized : unpack_bool(is_initialized)?,
+            investor_account : Pubkey::new_from_array(*investor_account),
+            fund_pool : Pubkey::new_from_array(*fund_pool),
+            index : u64::from_le_bytes(*index),
+            amount : u64::from_le_bytes(*amount),
+            token_count : u64::from_le_bytes(*token_count),
+            date : i64::from_le_bytes(*date),
+        })
+    }
+}
+
+
+
+
+
+
+// the seeds of the per-investor vesting account and its token vault,
+// followed by the fund pool and the investor's wallet
+pub const VESTING_SEED : &[u8] = b"vesting";
+
+pub const VESTING_VAULT_SEED : &[u8] = b"vesting_vault";
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VestingAccount {
+
+    pub is_initialized : bool,
+
+    pub fund_pool : Pubkey,
+
+    pub investor : Pubkey,
+
+    pub vault : Pubkey,
+
+    // both in the token base units
+    pub total_amount : u64,
+
+    pub claimed_amount : u64,
+}
+
+impl VestingAccount {
+
+    pub fn new() -> Self {
+
+        VestingAccount {
+            is_initialized : false,
+            fund_pool : Pubkey::default(),
+            investor : Pubkey::default(),
+            vault : Pubkey::default(),
+            total_amount : 0,
+            claimed_amount : 0,
+        }
+    }
+}
+
+impl Default for VestingAccount {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for VestingAccount {}
+
+impl IsInitialized for VestingAccount {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const VESTING_ACCOUNT_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + PUBKEY_BYTES + 8 + 8;
+
+impl Pack for VestingAccount {
+
+    const LEN: usize = VESTING_ACCOUNT_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, VESTING_ACCOUNT_LENGTH];
+
+        let (is_initialized, fund_pool, investor, vault, total_amount, claimed_amount) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        fund_pool.copy_from_slice(self.fund_pool.as_ref());
+        investor.copy_from_slice(self.investor.as_ref());
+        vault.copy_from_slice(self.vault.as_ref());
+        *total_amount = self.total_amount.to_le_bytes();
+        *claimed_amount = self.claimed_amount.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, VESTING_ACCOUNT_LENGTH];
+
+        let (is_initialized, fund_pool, investor, vault, total_amount, claimed_amount) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8];
+
+        Ok(VestingAccount {
+            is_initialized : unpack_bool(is_initialized)?,
+            fund_pool : Pubkey::new_from_array(*fund_pool),
+            investor : Pubkey::new_from_array(*investor),
+            vault : Pubkey::new_from_array(*vault),
+            total_amount : u64::from_le_bytes(*total_amount),
+            claimed_amount : u64::from_le_bytes(*claimed_amount),
+        })
+    }
+}
+
+
+
+
+/*
+The whitelist tier of an investor in a fund pool, stored in 
+the PDA derived from WHITELIST_SEED, tier 0 is no tier
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhitelistEntry {
+
+    pub is_initialized : bool,
+
+    pub fund_pool : Pubkey,
+
+    pub investor : Pubkey,
+
+    pub tier : u8,
+}
+
+impl WhitelistEntry {
+
+    pub fn new() -> Self {
+
+        WhitelistEntry {
+            is_initialized : false,
+            fund_pool : Pubkey::default(),
+            investor : Pubkey::default(),
+            tier : 0,
+        }
+    }
+}
+
+impl Default for WhitelistEntry {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for WhitelistEntry {}
+
+impl IsInitialized for WhitelistEntry {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const WHITELIST_ENTRY_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 1;
+
+impl Pack for WhitelistEntry {
+
+    const LEN: usize = WHITELIST_ENTRY_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, WHITELIST_ENTRY_LENGTH];
+
+        let (is_initialized, fund_pool, investor, tier) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1];
+
+        pack_bool(self.is_initialized, is_initialized);
+        fund_pool.copy_from_slice(self.fund_pool.as_ref());
+        investor.copy_from_slice(self.investor.as_ref());
+        *tier = self.tier.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, WHITELIST_ENTRY_LENGTH];
+
+        let (is_initialized, fund_pool, investor, tier) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1];
+
+        Ok(WhitelistEntry {
+            is_initialized : unpack_bool(is_initialized)?,
+            fund_pool : Pubkey::new_from_array(*fund_pool),
+            investor : Pubkey::new_from_array(*investor),
+            tier : u8::from_le_bytes(*tier),
+        })
+    }
+}
+
+
+
+
+// the seed of an attestation, followed by the verifier and the wallet
+pub const ATTESTATION_SEED : &[u8] = b"attestation";
+
+
+/*
+A KYC attestation of a wallet, created and signed by the verifier.
+The jurisdiction is the numeric ISO 3166 country code
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attestation {
+
+    pub is_initialized : bool,
+
+    pub verifier : Pubkey,
+
+    pub wallet : Pubkey,
+
+    pub jurisdiction : u16,
+
+    pub issued_at : UnixTimestamp,
+
+    pub expiry : UnixTimestamp,
+}
+
+impl Attestation {
+
+    pub fn new() -> Self {
+
+        Attestation {
+            is_initialized : false,
+            verifier : Pubkey::default(),
+            wallet : Pubkey::default(),
+            jurisdiction : 0,
+            issued_at : 0,
+            expiry : 0,
+        }
+    }
+
+
+    pub fn is_valid(&self, verifier : &Pubkey, wallet : &Pubkey, now : UnixTimestamp) -> bool {
+
+        self.is_initialized && self.verifier == *verifier && 
+        self.wallet == *wallet && now < self.expiry
+    }
+}
+
+impl Default for Attestation {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for Attestation {}
+
+impl IsInitialized for Attestation {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const ATTESTATION_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 2 + 8 + 8;
+
+impl Pack for Attestation {
+
+    const LEN: usize = ATTESTATION_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, ATTESTATION_LENGTH];
+
+        let (is_initialized, verifier, wallet, jurisdiction, issued_at, expiry) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 2, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        verifier.copy_from_slice(self.verifier.as_ref());
+        wallet.copy_from_slice(self.wallet.as_ref());
+        *jurisdiction = self.jurisdiction.to_le_bytes();
+        *issued_at = self.issued_at.to_le_bytes();
+        *expiry = self.expiry.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, ATTESTATION_LENGTH];
+
+        let (is_initialized, verifier, wallet, jurisdiction, issued_at, expiry) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 2, 8, 8];
+
+        Ok(Attestation {
+            is_initialized : unpack_bool(is_initialized)?,
+            verifier : Pubkey::new_from_array(*verifier),
+            wallet : Pubkey::new_from_array(*wallet),
+            jurisdiction : u16::from_le_bytes(*jurisdiction),
+            issued_at : i64::from_le_bytes(*issued_at),
+            expiry : i64::from_le_bytes(*expiry),
+        })
+    }
+}
+
+
+
+
+// the seed of a referrer's totals, followed by the referrer's wallet
+pub const REFERRAL_SEED : &[u8] = b"referral";
+
+
+/*
+The totals of a referrer across the fund pools, for the leaderboard
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Referral {
+
+    pub is_initialized : bool,
+
+    pub referrer : Pubkey,
+
+    pub referral_count : u64,
+
+    // the lamports invested by the referred investors
+    pub referred_lamports : u64,
+
+    pub reward_lamports : u64,
+}
+
+impl Referral {
+
+    pub fn new() -> Self {
+
+        Referral {
+            is_initialized : false,
+            referrer : Pubkey::default(),
+            referral_count : 0,
+            referred_lamports : 0,
+            reward_lamports : 0,
+        }
+    }
+}
+
+impl Default for Referral {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for Referral {}
+
+impl IsInitialized for Referral {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const REFERRAL_LENGTH : usize = 1 + PUBKEY_BYTES + 8 + 8 + 8;
+
+impl Pack for Referral {
+
+    const LEN: usize = REFERRAL_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, REFERRAL_LENGTH];
+
+        let (is_initialized, referrer, referral_count, referred_lamports, reward_lamports) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, 8, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        referrer.copy_from_slice(self.referrer.as_ref());
+        *referral_count = self.referral_count.to_le_bytes();
+        *referred_lamports = self.referred_lamports.to_le_bytes();
+        *reward_lamports = self.reward_lamports.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, REFERRAL_LENGTH];
+
+        let (is_initialized, referrer, referral_count, referred_lamports, reward_lamports) =
+        array_refs![input, 1, PUBKEY_BYTES, 8, 8, 8];
+
+        Ok(Referral {
+            is_initialized : unpack_bool(is_initialized)?,
+            referrer : Pubkey::new_from_array(*referrer),
+            referral_count : u64::from_le_bytes(*referral_count),
+            referred_lamports : u64::from_le_bytes(*referred_lamports),
+            reward_lamports : u64::from_le_bytes(*reward_lamports),
+        })
+    }
+}
+
+
+
+
+/*
+The minimal price feed read by the Oracle pricing mode, the price of 
+a SOL in USD is price * 10 ^ expo, give or take the confidence (conf)
+in the same scale. The feed is owned and written by the oracle program
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceFeed {
+
+    pub price : i64,
+
+    pub expo : i32,
+
+    pub conf : u64,
+
+    pub publish_time : UnixTimestamp,
+}
+
+impl PriceFeed {
+
+    /*
+    A usable price is positive, not published in the future, 
+    no older than the max age and with the confidence within the max basis points of the price
+    */
+    pub fn check(&self, now : UnixTimestamp, max_age : i64, max_conf_bps : u16) -> Result<bool, PoolError> {
+
+        if self.price <= 0 {
+
+            return Err(PoolError::InvalidOracleAccount);
+        }
+
+        if self.publish_time > now {
+
+            return Err(PoolError::InvalidOracleAccount);
+        }
+
+        if now.saturating_sub(self.publish_time) > max_age {
+
+            return Err(PoolError::StalePrice);
+        }
+
+        if (self.conf as u128) * (MAX_BASIS_POINTS as u128) > (self.price as u128) * (max_conf_bps as u128) {
+
+            return Err(PoolError::PriceUncertain);
+        }
+
+        Ok(true)
+    }
+
+
+    /*
+    The lamports of the micro-USD (USD_SCALE) at the price, 
+    rounded up in favour of the pool
+    */
+    pub fn usd_to_lamports(&self, usd : u128) -> Option<u64> {
+
+        if self.price <= 0 {
+
+            return None;
+        }
+
+        let mut num = usd.checked_mul(LAMPORTS_PER_SOL as u128)?;
+        let mut den = (self.price as u128).checked_mul(USD_SCALE)?;
+
+        let scale = 10u128.checked_pow(self.expo.unsigned_abs())?;
+
+        if self.expo < 0 {
+
+            num = num.checked_mul(scale)?;
+        }
+        else {
+
+            den = den.checked_mul(scale)?;
+        }
+
+        let mut lamports = num / den;
+
+        if num % den > 0 {
+
+            lamports += 1;
+        }
+
+        u64::try_from(lamports).ok()
+    }
+}
+
+impl Sealed for PriceFeed {}
+
+
+const PRICE_FEED_LENGTH : usize = 8 + 4 + 8 + 8;
+
+impl Pack for PriceFeed {
+
+    const LEN: usize = PRICE_FEED_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, PRICE_FEED_LENGTH];
+
+        let (price, expo, conf, publish_time) = mut_array_refs![output, 8, 4, 8, 8];
+
+        *price = self.price.to_le_bytes();
+        *expo = self.expo.to_le_bytes();
+        *conf = self.conf.to_le_bytes();
+        *publish_time = self.publish_time.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, PRICE_FEED_LENGTH];
+
+        let (price, expo, conf, publish_time) = array_refs![input, 8, 4, 8, 8];
+
+        Ok(PriceFeed {
+            price : i64::from_le_bytes(*price),
+            expo : i32::from_le_bytes(*expo),
+            conf : u64::from_le_bytes(*conf),
+            publish_time : i64::from_le_bytes(*publish_time),
+        })
+    }
+}
+
+
+
+
+// the seed of a bidder's bid, followed by the fund pool and the bidder
+pub const BID_SEED : &[u8] = b"bid";
+
+
+/*
+The bids of a bidder in a Dutch auction, the locked lamports 
+are in the pool_pda until claimed after the auction settles
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bid {
+
+    pub is_initialized : bool,
+
+    pub fund_pool : Pubkey,
+
+    pub bidder : Pubkey,
+
+    pub token_count : u64,
+
+    pub locked_lamports : u64,
+
+    pub is_claimed : bool,
+}
+
+impl Bid {
+
+    pub fn new() -> Self {
+
+        Bid {
+            is_initialized : false,
+            fund_pool : Pubkey::default(),
+            bidder : Pubkey::default(),
+            token_count : 0,
+            locked_lamports : 0,
+            is_claimed : false,
+        }
+    }
+}
+
+impl Default for Bid {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for Bid {}
+
+impl IsInitialized for Bid {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const BID_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 8 + 8 + 1;
+
+impl Pack for Bid {
+
+    const LEN: usize = BID_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, BID_LENGTH];
+
+        let (is_initialized, fund_pool, bidder, token_count, locked_lamports, is_claimed) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8, 1];
+
+        pack_bool(self.is_initialized, is_initialized);
+        fund_pool.copy_from_slice(self.fund_pool.as_ref());
+        bidder.copy_from_slice(self.bidder.as_ref());
+        *token_count = self.token_count.to_le_bytes();
+        *locked_lamports = self.locked_lamports.to_le_bytes();
+        pack_bool(self.is_claimed, is_claimed);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, BID_LENGTH];
+
+        let (is_initialized, fund_pool, bidder, token_count, locked_lamports, is_claimed) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 8, 8, 1];
+
+        Ok(Bid {
+            is_initialized : unpack_bool(is_initialized)?,
+            fund_pool : Pubkey::new_from_array(*fund_pool),
+            bidder : Pubkey::new_from_array(*bidder),
+            token_count : u64::from_le_bytes(*token_count),
+            locked_lamports : u64::from_le_bytes(*locked_lamports),
+            is_claimed : unpack_bool(is_claimed)?,
+        })
+    }
+}
+
+
+
+
+// the seed of the token escrow of a sell order, followed by the order account
+pub const ORDER_ESCROW_SEED : &[u8] = b"order_escrow";
+
+
+#[derive(Clone, Copy, Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum OrderSide {
+
+    // the lamports are escrowed in the order account 
+    Buy = 0,
+
+    // the tokens are escrowed in the order's escrow token account
+    Sell = 1,
+}
+
+
+/*
+A limit order of the pool tokens, priced in lamports per token count.
+The token count is what's left to fill
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Order {
+
+    pub is_initialized : bool,
+
+    pub fund_pool : Pubkey,
+
+    pub owner : Pubkey,
+
+    // the owner's investor record, credited or debited on fills
+    pub investor_account : Pubkey,
+
+    // the owner's token account, which receives the bought tokens
+    pub token_account : Pubkey,
+
+    pub escrow : Pubkey,
+
+    pub side : OrderSide,
+
+    pub price : u64,
+
+    pub token_count : u64,
+
+    pub date : UnixTimestamp,
+}
+
+impl Order {
+
+    pub fn new() -> Self {
+
+        Order {
+            is_initialized : false,
+            fund_pool : Pubkey::default(),
+            owner : Pubkey::default(),
+            investor_account : Pubkey::default(),
+            token_account : Pubkey::default(),
+            escrow : Pubkey::default(),
+            side : OrderSide::Buy,
+            price : 0,
+            token_count : 0,
+            date : 0,
+        }
+    }
+}
+
+impl Default for Order {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl Sealed for Order {}
+
+impl IsInitialized for Order {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
+    }
+}
+
+
+const ORDER_LENGTH : usize = 1 + (PUBKEY_BYTES * 5) + 1 + 8 + 8 + 8;
+
+impl Pack for Order {
+
+    const LEN: usize = ORDER_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, ORDER_LENGTH];
+
+        let (is_initialized, fund_pool, owner, investor_account, token_account, escrow, 
+            side, price, token_count, date) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, 
+        1, 8, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        fund_pool.copy_from_slice(self.fund_pool.as_ref());
+        owner.copy_from_slice(self.owner.as_ref());
+        investor_account.copy_from_slice(self.investor_account.as_ref());
+        token_account.copy_from_slice(self.token_account.as_ref());
+        escrow.copy_from_slice(self.escrow.as_ref());
+        *side = u8::from(self.side).to_le_bytes();
+        *price = self.price.to_le_bytes();
+        *token_count = self.token_count.to_le_bytes();
+        *date = self.date.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, ORDER_LENGTH];
+
+        let (is_initialized, fund_pool, owner, investor_account, token_account, escrow, 
+            side, price, token_count, date) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, PUBKEY_BYTES, 
+        1, 8, 8, 8];
+
+        Ok(Order {
+            is_initialized : unpack_bool(is_initialized)?,
+            fund_pool : Pubkey::new_from_array(*fund_pool),
+            owner : Pubkey::new_from_array(*owner),
+            investor_account : Pubkey::new_from_array(*investor_account),
+            token_account : Pubkey::new_from_array(*token_account),
+            escrow : Pubkey::new_from_array(*escrow),
+            side : OrderSide::try_from_primitive(u8::from_le_bytes(*side))
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+            price : u64::from_le_bytes(*price),
+            token_count : u64::from_le_bytes(*token_count),
+            date : i64::from_le_bytes(*date),
+        })
+    }
+}
+
+
+
+
+pub const PROGRAM_CONFIG_SEED : &[u8] = b"config";
+
+pub const MAX_BASIS_POINTS : u16 = 10_000;
+
+/*
+The program wide config, a singleton stored in the PDA
+derived from PROGRAM_CONFIG_SEED. A zero max means no limit
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramConfig {
+
+    pub is_initialized : bool,
+
+    pub admin : Pubkey,
+
+    // receives the protocol fee of each investment
+    pub treasury : Pubkey,
+
+    pub protocol_fee_bps : u16,
+
+    pub is_paused : bool,
+
+    pub min_token_count : u64,
+
+    pub max_token_count : u64,
+
+    pub min_token_to_lamport_ratio : u64,
+
+    pub max_fee_in_lamports : u64,
+}
+
+impl ProgramConfig {
+
+    pub fn new() -> Self {
+
+        ProgramConfig {
+            is_initialized : false,
+            admin : Pubkey::default(),
+            treasury : Pubkey::default(),
+            protocol_fee_bps : 0,
+            is_paused : false,
+            min_token_count : 0,
+            max_token_count : 0,
+            min_token_to_lamport_ratio : 0,
+            max_fee_in_lamports : 0,
+        }
+    }
+}
+
+impl Default for ProgramConfig {
+
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+impl ProgramConfig {
+
+    pub fn protocol_fee(&self, amount : u64) -> Option<u64> {
+
+        let fee = (amount as u128).checked_mul(self.protocol_fee_bps as u128)?
+        / (MAX_BASIS_POINTS as u128);
+
+        u64::try_from(fee).ok()
+    }
 
 
-        investor.copy_from_slice(self.investor.as_ref());
-        pool_address.copy_from_slice(self.pool_address.as_ref());
-        address.copy_from_slice(self.address.as_ref());
-        
-        *token_count = self.token_count.to_le_bytes();
-        *amount = self.amount.to_le_bytes();
-        token_account.copy_from_slice(self.token_account.as_ref());
-        *date = self.date.to_le_bytes();
+    pub fn validate_fund_pool(&self, token_count : u64, token_to_lamport_ratio : u64, 
+        fee_in_lamports : u64) -> Result<bool, PoolError> {
 
-    }
+        if token_count < self.min_token_count ||
+        (self.max_token_count > 0 && token_count > self.max_token_count) {
 
-    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-   
-        let input = array_ref![src, 0, INVESTOR_DATA_SIZE];
-       
-        let (investor,pool_address, address, amount,token_account,token_count, date)  =
+            return Err(PoolError::InvalidPoolParameters);
+        }
 
-        array_refs![input, PUBKEY_BYTES, PUBKEY_BYTES,
-        PUBKEY_BYTES, 8, PUBKEY_BYTES, 8, 8 ];
+        if token_to_lamport_ratio < self.min_token_to_lamport_ratio {
 
-        let investor = Pubkey::new_from_array(*investor);
-        let pool_address = Pubkey::new_from_array(*pool_address);
-        let token_account = Pubkey::new_from_array(*token_account);
-        let address = Pubkey::new_from_array(*address);
-        let amount = u64::from_le_bytes(*amount);
-        let token_count = u64::from_le_bytes(*token_count);
-        let date = i64::from_le_bytes(*date);
+            return Err(PoolError::InvalidPoolParameters);
+        }
 
-        Ok( Investor{
-            investor : investor,
-            address : address,
-            pool_address : pool_address,
-            token_account : token_account,
-            amount :amount, 
-            token_count : token_count,
-            date : date, 
-        })
+        if self.max_fee_in_lamports > 0 && fee_in_lamports > self.max_fee_in_lamports {
+
+            return Err(PoolError::InvalidPoolParameters);
+        }
+
+        Ok(true)
+    }
+}
+
+impl Sealed for ProgramConfig {}
+
+impl IsInitialized for ProgramConfig {
+    fn is_initialized(&self) -> bool {
+
+        self.is_initialized
     }
 }
 
 
+const PROGRAM_CONFIG_LENGTH : usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 2 + 1 + 8 + 8 + 8 + 8;
+
+impl Pack for ProgramConfig {
+
+    const LEN: usize = PROGRAM_CONFIG_LENGTH;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+
+        let output = array_mut_ref![dst, 0, PROGRAM_CONFIG_LENGTH];
+
+        let (is_initialized, admin, treasury, protocol_fee_bps, is_paused,
+        min_token_count, max_token_count, min_token_to_lamport_ratio, max_fee_in_lamports) =
+        mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 2, 1, 8, 8, 8, 8];
+
+        pack_bool(self.is_initialized, is_initialized);
+        admin.copy_from_slice(self.admin.as_ref());
+        treasury.copy_from_slice(self.treasury.as_ref());
+        *protocol_fee_bps = self.protocol_fee_bps.to_le_bytes();
+        pack_bool(self.is_paused, is_paused);
+        *min_token_count = self.min_token_count.to_le_bytes();
+        *max_token_count = self.max_token_count.to_le_bytes();
+        *min_token_to_lamport_ratio = self.min_token_to_lamport_ratio.to_le_bytes();
+        *max_fee_in_lamports = self.max_fee_in_lamports.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+
+        let input = array_ref![src, 0, PROGRAM_CONFIG_LENGTH];
+
+        let (is_initialized, admin, treasury, protocol_fee_bps, is_paused,
+        min_token_count, max_token_count, min_token_to_lamport_ratio, max_fee_in_lamports) =
+        array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 2, 1, 8, 8, 8, 8];
+
+        Ok(ProgramConfig {
+            is_initialized : unpack_bool(is_initialized)?,
+            admin : Pubkey::new_from_array(*admin),
+            treasury : Pubkey::new_from_array(*treasury),
+            protocol_fee_bps : u16::from_le_bytes(*protocol_fee_bps),
+            is_paused : unpack_bool(is_paused)?,
+            min_token_count : u64::from_le_bytes(*min_token_count),
+            max_token_count : u64::from_le_bytes(*max_token_count),
+            min_token_to_lamport_ratio : u64::from_le_bytes(*min_token_to_lamport_ratio),
+            max_fee_in_lamports : u64::from_le_bytes(*max_fee_in_lamports),
+        })
+    }
+}
 
 
 
@@ -788,4 +2883,487 @@ pub fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
             Err(ProgramError::InvalidAccountData)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+
+    fn vesting_pool(schedule : VestingSchedule, cliff : i64, duration : i64) -> FundPool {
+
+        let mut fp = FundPool::new(true);
+        fp.vesting_schedule = schedule;
+        fp.vesting_start = 1_000;
+        fp.vesting_cliff = cliff;
+        fp.vesting_duration = duration;
+        fp
+    }
+
+
+    #[test]
+    fn test_vested_amount_without_schedule() {
+
+        let fp = vesting_pool(VestingSchedule::None, 0, 0);
+
+        assert_eq!(fp.vested_amount(500, 0), 500);
+        assert_eq!(fp.vested_amount(500, 1_000), 500);
+    }
+
+
+    #[test]
+    fn test_vested_amount_cliff() {
+
+        let fp = vesting_pool(VestingSchedule::Cliff, 100, 0);
+
+        assert_eq!(fp.vested_amount(500, 999), 0);
+        assert_eq!(fp.vested_amount(500, 1_099), 0);
+        assert_eq!(fp.vested_amount(500, 1_100), 500);
+        assert_eq!(fp.vested_amount(500, i64::MAX), 500);
+    }
+
+
+    #[test]
+    fn test_vested_amount_linear() {
+
+        let fp = vesting_pool(VestingSchedule::Linear, 100, 400);
+
+        // nothing before the start or the cliff
+        assert_eq!(fp.vested_amount(1_000, 0), 0);
+        assert_eq!(fp.vested_amount(1_000, 1_099), 0);
+
+        // the cliff releases what has accrued since the start
+        assert_eq!(fp.vested_amount(1_000, 1_100), 250);
+        assert_eq!(fp.vested_amount(1_000, 1_200), 500);
+
+        // rounded down in between
+        assert_eq!(fp.vested_amount(999, 1_201), 501);
+
+        assert_eq!(fp.vested_amount(1_000, 1_400), 1_000);
+        assert_eq!(fp.vested_amount(1_000, i64::MAX), 1_000);
+    }
+
+
+    #[test]
+    fn test_vested_amount_linear_no_overflow() {
+
+        let fp = vesting_pool(VestingSchedule::Linear, 0, 400);
+
+        assert_eq!(fp.vested_amount(u64::MAX, 1_200), u64::MAX / 2);
+
+        // a start far in the past saturates the elapsed time
+        let mut fp = vesting_pool(VestingSchedule::Linear, 0, 400);
+        fp.vesting_start = i64::MIN;
+
+        assert_eq!(fp.vested_amount(1_000, i64::MAX), 1_000);
+    }
+
+
+    fn leaf(wallet : &Pubkey, allocation : u64) -> [u8; HASH_BYTES] {
+
+        hashv(&[wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+    }
+
+
+    fn node(a : &[u8; HASH_BYTES], b : &[u8; HASH_BYTES]) -> [u8; HASH_BYTES] {
+
+        if a <= b { hashv(&[a, b]).to_bytes() } else { hashv(&[b, a]).to_bytes() }
+    }
+
+
+    #[test]
+    fn test_is_allowlisted_single_leaf() {
+
+        let wallet = Pubkey::new_unique();
+
+        let mut fp = FundPool::new(true);
+
+        assert!(!fp.has_allowlist());
+
+        fp.allowlist_root = leaf(&wallet, 100);
+
+        assert!(fp.has_allowlist());
+        assert!(fp.is_allowlisted(&wallet, 100, &[]));
+        assert!(!fp.is_allowlisted(&wallet, 101, &[]));
+        assert!(!fp.is_allowlisted(&Pubkey::new_unique(), 100, &[]));
+    }
+
+
+    #[test]
+    fn test_is_allowlisted_proofs() {
+
+        let wallets : Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let leaves : Vec<[u8; HASH_BYTES]> = wallets.iter().enumerate()
+        .map(|(i, w)| leaf(w, i as u64 * 10)).collect();
+
+        let left = node(&leaves[0], &leaves[1]);
+        let right = node(&leaves[2], &leaves[3]);
+
+        let mut fp = FundPool::new(true);
+        fp.allowlist_root = node(&left, &right);
+
+        assert!(fp.is_allowlisted(&wallets[0], 0, &[leaves[1], right]));
+        assert!(fp.is_allowlisted(&wallets[1], 10, &[leaves[0], right]));
+        assert!(fp.is_allowlisted(&wallets[2], 20, &[leaves[3], left]));
+        assert!(fp.is_allowlisted(&wallets[3], 30, &[leaves[2], left]));
+
+        // the allocation is part of the leaf
+        assert!(!fp.is_allowlisted(&wallets[3], 31, &[leaves[2], left]));
+
+        // another member's proof, a partial proof or the wrong order of levels
+        assert!(!fp.is_allowlisted(&wallets[0], 0, &[leaves[3], left]));
+        assert!(!fp.is_allowlisted(&wallets[0], 0, &[leaves[1]]));
+        assert!(!fp.is_allowlisted(&wallets[0], 0, &[right, leaves[1]]));
+
+        // an inner node isn't a leaf
+        assert!(!fp.is_allowlisted(&Pubkey::new_unique(), 0, &[right]));
+    }
+
+
+    fn priced_pool(mode : PricingMode, ratio : u64, curve_param : u64, sold : u64) -> FundPool {
+
+        let mut fp = FundPool::new(true);
+        fp.pricing_mode = mode;
+        fp.token_to_lamport_ratio = ratio;
+        fp.curve_param = curve_param;
+        fp.token_count = 1_000;
+        fp.rm_token_count = 1_000 - sold;
+        fp
+    }
+
+
+    #[test]
+    fn test_curve_pow() {
+
+        assert_eq!(curve_pow(3 * CURVE_SCALE, 0), Some(CURVE_SCALE));
+        assert_eq!(curve_pow(2 * CURVE_SCALE, 1), Some(2 * CURVE_SCALE));
+        assert_eq!(curve_pow(2 * CURVE_SCALE, 10), Some(1_024 * CURVE_SCALE));
+        assert_eq!(curve_pow(CURVE_SCALE / 2, 3), Some(CURVE_SCALE / 8));
+
+        // 1.1 ^ 2, 1.1 ^ 3 in the fixed point
+        assert_eq!(curve_pow(1_100_000_000, 2), Some(1_210_000_000));
+        assert_eq!(curve_pow(1_100_000_000, 3), Some(1_331_000_000));
+
+        // each step rounds down
+        assert_eq!(curve_pow(CURVE_SCALE / 3, 2), Some(111_111_110));
+
+        assert_eq!(curve_pow(2 * CURVE_SCALE, 200), None);
+    }
+
+
+    #[test]
+    fn test_purchase_cost_fixed() {
+
+        let fp = priced_pool(PricingMode::Fixed, 1_000, 0, 500);
+
+        assert_eq!(fp.purchase_cost(10, 0), Some(10_000));
+        assert_eq!(fp.purchase_cost(0, 0), Some(0));
+
+        let fp = priced_pool(PricingMode::Fixed, u64::MAX, 0, 0);
+
+        assert_eq!(fp.purchase_cost(1, 0), Some(u64::MAX));
+        assert_eq!(fp.purchase_cost(2, 0), None);
+    }
+
+
+    #[test]
+    fn test_purchase_cost_linear() {
+
+        // 100, 110, 120
+        let fp = priced_pool(PricingMode::Linear, 100, 10, 0);
+
+        assert_eq!(fp.purchase_cost(3, 0), Some(330));
+        assert_eq!(fp.purchase_cost(1, 0), Some(100));
+
+        // 150, 160, 170 after 5 sold
+        let fp = priced_pool(PricingMode::Linear, 100, 10, 5);
+
+        assert_eq!(fp.purchase_cost(3, 0), Some(480));
+
+        // buying in parts costs the same as at once
+        let a = priced_pool(PricingMode::Linear, 100, 10, 0).purchase_cost(5, 0).unwrap();
+        let b = priced_pool(PricingMode::Linear, 100, 10, 5).purchase_cost(3, 0).unwrap();
+
+        assert_eq!(a + b, priced_pool(PricingMode::Linear, 100, 10, 0).purchase_cost(8, 0).unwrap());
+
+        let fp = priced_pool(PricingMode::Linear, 100, u64::MAX, 0);
+
+        assert_eq!(fp.purchase_cost(3, 0), None);
+    }
+
+
+    #[test]
+    fn test_purchase_cost_exponential() {
+
+        // without growth it's the fixed price
+        let fp = priced_pool(PricingMode::Exponential, 1_000, 0, 10);
+
+        assert_eq!(fp.purchase_cost(3, 0), Some(3_000));
+
+        // 10% per token, 1000 then 1100
+        let fp = priced_pool(PricingMode::Exponential, 1_000, 100_000_000, 0);
+
+        assert_eq!(fp.purchase_cost(2, 0), Some(2_100));
+
+        // 1210 after 2 sold
+        let fp = priced_pool(PricingMode::Exponential, 1_000, 100_000_000, 2);
+
+        assert_eq!(fp.purchase_cost(1, 0), Some(1_210));
+
+        // rounded down, the smallest growth on the smallest price
+        let fp = priced_pool(PricingMode::Exponential, 1, 1, 0);
+
+        assert_eq!(fp.purchase_cost(1, 0), Some(1));
+
+        // doubling per token overflows the fixed point
+        let fp = priced_pool(PricingMode::Exponential, 1, CURVE_SCALE as u64, 0);
+
+        assert_eq!(fp.purchase_cost(200, 0), None);
+    }
+
+
+    #[test]
+    fn test_purchase_cost_tiered_by_sold() {
+
+        let mut fp = priced_pool(PricingMode::TieredBySold, 500, 0, 0);
+
+        fp.set_price_tiers(vec![
+            PriceTier { threshold : 10, price : 100 },
+            PriceTier { threshold : 20, price : 200 },
+        ]).unwrap();
+
+        assert_eq!(fp.purchase_cost(5, 0), Some(500));
+
+        // split across both tiers and past the last one
+        assert_eq!(fp.purchase_cost(25, 0), Some(10 * 100 + 10 * 200 + 5 * 500));
+
+        fp.rm_token_count = fp.token_count - 15;
+
+        assert_eq!(fp.purchase_cost(10, 0), Some(5 * 200 + 5 * 500));
+
+        fp.rm_token_count = fp.token_count - 20;
+
+        assert_eq!(fp.purchase_cost(2, 0), Some(1_000));
+    }
+
+
+    #[test]
+    fn test_purchase_cost_tiered_by_time() {
+
+        let mut fp = priced_pool(PricingMode::TieredByTime, 300, 0, 0);
+
+        fp.set_price_tiers(vec![
+            PriceTier { threshold : 1_000, price : 100 },
+            PriceTier { threshold : 2_000, price : 200 },
+        ]).unwrap();
+
+        assert_eq!(fp.purchase_cost(2, -5), Some(200));
+        assert_eq!(fp.purchase_cost(2, 999), Some(200));
+        assert_eq!(fp.purchase_cost(2, 1_000), Some(400));
+        assert_eq!(fp.purchase_cost(2, 2_000), Some(600));
+    }
+
+
+    #[test]
+    fn test_price_tiers_rejected() {
+
+        let mut fp = priced_pool(PricingMode::TieredBySold, 500, 0, 0);
+
+        assert!(fp.set_price_tiers(vec![
+            PriceTier { threshold : 20, price : 100 },
+            PriceTier { threshold : 10, price : 200 },
+        ]).is_err());
+
+        assert!(fp.set_price_tiers(vec![PriceTier { threshold : 10, price : 0 }]).is_err());
+    }
+
+
+    #[test]
+    fn test_purchase_cost_unpriced_modes() {
+
+        assert_eq!(priced_pool(PricingMode::DutchAuction, 100, 0, 0).purchase_cost(1, 0), None);
+        assert_eq!(priced_pool(PricingMode::Oracle, 100, 0, 0).purchase_cost(1, 0), None);
+    }
+
+
+    // Investor::new() reads the clock, not available to the unit tests
+    fn investor(token_count : u64, escrowed_token_count : u64, vesting_token_count : u64) -> Investor {
+
+        Investor {
+            investor : Pubkey::new_unique(),
+            pool_address : Pubkey::new_unique(),
+            address : Pubkey::new_unique(),
+            amount : 0,
+            token_account : Pubkey::default(),
+            token_count,
+            date : 0,
+            investment_count : 0,
+            escrowed_token_count,
+            vesting_token_count,
+        }
+    }
+
+
+    #[test]
+    fn test_available_token_count() {
+
+        assert_eq!(investor(100, 0, 0).available_token_count(), 100);
+
+        // neither the escrowed nor the still vesting tokens are in the wallet
+        assert_eq!(investor(100, 30, 0).available_token_count(), 70);
+        assert_eq!(investor(100, 0, 40).available_token_count(), 60);
+        assert_eq!(investor(100, 30, 40).available_token_count(), 30);
+        assert_eq!(investor(100, 60, 60).available_token_count(), 0);
+    }
+
+
+    #[test]
+    fn test_unpack_legacy_fund_pool() {
+
+        let manager = Pubkey::new_unique();
+        let record = Pubkey::new_unique();
+        let investor = Pubkey::new_unique();
+
+        let mut src = vec![0; LEGACY_FUND_POOL_LENGTH];
+        src[0] = 1;
+        src[1..33].copy_from_slice(manager.as_ref());
+        src[201..209].copy_from_slice(&1_000u64.to_le_bytes());
+        src[209..217].copy_from_slice(&900u64.to_le_bytes());
+        src[228] = 1;
+        src[230..262].copy_from_slice(record.as_ref());
+        src[262..294].copy_from_slice(investor.as_ref());
+        src[294..302].copy_from_slice(&100u64.to_le_bytes());
+
+        let fp = FundPool::unpack_legacy(&src).unwrap();
+
+        assert_eq!(fp.layout_version, FUND_POOL_LAYOUT_VERSION);
+        assert_eq!(fp.manager, manager);
+        assert_eq!(fp.token_count, 1_000);
+        assert_eq!(fp.rm_token_count, 900);
+        assert_eq!(fp.investor_count(), 1);
+
+        // the current layout doesn't pass for the original one
+        assert!(FundPool::unpack_legacy(&vec![0; FundPool::LEN]).is_err());
+    }
+
+
+    #[test]
+    fn test_unpack_other_layout_version() {
+
+        let mut dst = vec![0; FundPool::LEN];
+        FundPool::pack(FundPool::new(true), &mut dst).unwrap();
+
+        assert!(FundPool::unpack(&dst).is_ok());
+
+        dst[1] = FUND_POOL_LAYOUT_VERSION + 1;
+
+        assert!(FundPool::unpack(&dst).is_err());
+    }
+
+
+    #[test]
+    fn test_unpack_legacy_investor() {
+
+        let mut src = vec![0; LEGACY_INVESTOR_DATA_SIZE];
+        src[PUBKEY_BYTES * 4 + 8..PUBKEY_BYTES * 4 + 16].copy_from_slice(&100u64.to_le_bytes());
+
+        let i = Investor::unpack_legacy(&src).unwrap();
+
+        assert_eq!(i.token_count, 100);
+        assert_eq!(i.vesting_token_count, 0);
+        assert!(Investor::unpack_legacy(&vec![0; Investor::LEN]).is_err());
+    }
+
+
+    #[test]
+    fn test_auction_price() {
+
+        let mut fp = FundPool::new(true);
+        fp.set_auction(1_000, 200, 100, 500).unwrap();
+
+        assert_eq!(fp.auction_price(0), 1_000);
+        assert_eq!(fp.auction_price(100), 1_000);
+
+        // decays linearly, rounded up to the lamport
+        assert_eq!(fp.auction_price(300), 600);
+        assert_eq!(fp.auction_price(101), 998);
+
+        assert_eq!(fp.auction_price(500), 200);
+        assert_eq!(fp.auction_price(i64::MAX), 200);
+
+        assert!(fp.set_auction(100, 200, 100, 500).is_err());
+        assert!(fp.set_auction(1_000, 200, 500, 500).is_err());
+    }
+
+
+    #[test]
+    fn test_referral_reward() {
+
+        let mut fp = FundPool::new(true);
+        fp.fee_in_lamports = 10_000;
+
+        assert_eq!(fp.referral_reward(), Some(0));
+
+        fp.referral_bps = 250;
+        assert_eq!(fp.referral_reward(), Some(250));
+
+        fp.fee_in_lamports = u64::MAX;
+        fp.referral_bps = MAX_BASIS_POINTS;
+        assert_eq!(fp.referral_reward(), Some(u64::MAX));
+    }
+
+
+    #[test]
+    fn test_protocol_fee() {
+
+        let mut config = ProgramConfig::new();
+
+        assert_eq!(config.protocol_fee(1_000_000), Some(0));
+
+        config.protocol_fee_bps = 30;
+        assert_eq!(config.protocol_fee(1_000_000), Some(3_000));
+
+        // rounded down
+        assert_eq!(config.protocol_fee(333), Some(0));
+        assert_eq!(config.protocol_fee(u64::MAX), Some(u64::MAX / 10_000 * 30 + 4));
+    }
+
+
+    #[test]
+    fn test_amount_of() {
+
+        let mut i = investor(300, 0, 0);
+        i.amount = 1_000;
+
+        assert_eq!(i.amount_of(300), Some(1_000));
+        assert_eq!(i.amount_of(150), Some(500));
+        assert_eq!(i.amount_of(100), Some(333));
+        assert_eq!(i.amount_of(0), Some(0));
+        assert_eq!(i.amount_of(301), None);
+    }
+
+
+    #[test]
+    fn test_check_investor_token_count() {
+
+        let mut fp = FundPool::new(true);
+
+        // no limits
+        assert!(fp.check_investor_token_count(0, 0).is_ok());
+        assert!(fp.check_investor_token_count(u64::MAX, 0).is_ok());
+
+        fp.set_investor_limits(10, 100, vec![WhitelistTier { min_tokens : 50, max_tokens : 0 }]).unwrap();
+
+        assert_eq!(fp.check_investor_token_count(9, 0), Err(PoolError::BelowMinInvestment));
+        assert!(fp.check_investor_token_count(10, 0).is_ok());
+        assert!(fp.check_investor_token_count(100, 0).is_ok());
+        assert_eq!(fp.check_investor_token_count(101, 0), Err(PoolError::AboveMaxInvestment));
+
+        // the tier's own limits, no max
+        assert_eq!(fp.check_investor_token_count(49, 1), Err(PoolError::BelowMinInvestment));
+        assert!(fp.check_investor_token_count(1_000, 1).is_ok());
+
+        assert_eq!(fp.check_investor_token_count(50, 2), Err(PoolError::InvalidWhitelistTier));
+    }
+}
@@ -0,0 +1,559 @@
+/**
+ * The Oracle pricing mode against a mock oracle program,
+ * which publishes whatever price feed it's given
+ */
+use {
+    solafund2::{
+        error::PoolError,
+        processor::process_instruction,
+        state::{FundPool, Investor, MintMode, PriceFeed, PricingMode, UserPool, INVESTMENT_SEED, PROGRAM_CONFIG_SEED},
+    },
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program, sysvar,
+    },
+    spl_associated_token_account::get_associated_token_address,
+    solana_program_test::{processor, tokio, BanksClient, ProgramTest},
+    solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+};
+
+
+const MODULE_FUND_POOL : u8 = 1;
+
+const ACTION_SET_ORACLE_PRICING : u8 = 69;
+
+const MODULE_INVESTOR : u8 = 2;
+
+const ACTION_ADD_INVESTOR : u8 = 1;
+
+// $150 a SOL
+const SOL_PRICE : i64 = 15_000_000_000;
+
+const SOL_EXPO : i32 = -8;
+
+// $1.5 a token, in micro-USD
+const USD_PRICE : u64 = 1_500_000;
+
+const MAX_AGE : i64 = 60;
+
+const MAX_CONF_BPS : u16 = 100;
+
+
+/*
+The mock oracle writes the instruction data as the price feed
+of the first account
+*/
+fn process_mock_oracle(_program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+
+    let account_info_iter = &mut accounts.iter();
+
+    let feed_account = next_account_info(account_info_iter)?;
+
+    let feed = PriceFeed::unpack_unchecked(instruction_data)?;
+
+    PriceFeed::pack(feed, &mut feed_account.data.borrow_mut())
+}
+
+
+struct TestEnv {
+
+    program_id : Pubkey,
+
+    oracle_id : Pubkey,
+
+    feed : Pubkey,
+
+    fund_pool : Pubkey,
+
+    manager : Keypair,
+}
+
+
+/*
+The programs and the price feed, without the fund pool
+*/
+fn oracle_program_test(env : &TestEnv) -> ProgramTest {
+
+    let mut pt = ProgramTest::new("solafund2", env.program_id, processor!(process_instruction));
+
+    pt.add_program("mock_oracle", env.oracle_id, processor!(process_mock_oracle));
+
+    pt.add_account(env.feed, Account {
+        lamports : 1_000_000_000,
+        data : vec![0; PriceFeed::LEN],
+        owner : env.oracle_id,
+        ..Account::default()
+    });
+
+    pt
+}
+
+
+fn add_fund_pool(pt : &mut ProgramTest, env : &TestEnv, fp : FundPool) {
+
+    let mut data = vec![0; FundPool::LEN];
+    FundPool::pack(fp, &mut data).unwrap();
+
+    pt.add_account(env.fund_pool, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : env.program_id,
+        ..Account::default()
+    });
+}
+
+
+fn program_test(env : &TestEnv) -> ProgramTest {
+
+    let mut pt = oracle_program_test(env);
+
+    let mut fp = FundPool::new(true);
+    fp.manager = env.manager.pubkey();
+    fp.address = env.fund_pool;
+    fp.token_count = 1000;
+    fp.rm_token_count = 1000;
+    fp.token_to_lamport_ratio = 1000;
+
+    add_fund_pool(&mut pt, env, fp);
+
+    pt
+}
+
+
+fn test_env() -> TestEnv {
+
+    TestEnv {
+        program_id : solafund2::id(),
+        oracle_id : Pubkey::new_unique(),
+        feed : Pubkey::new_unique(),
+        fund_pool : Pubkey::new_unique(),
+        manager : Keypair::new(),
+    }
+}
+
+
+fn set_oracle_pricing_instruction(env : &TestEnv, manager : &Pubkey) -> Instruction {
+
+    let mut data = vec![MODULE_FUND_POOL, ACTION_SET_ORACLE_PRICING];
+    data.extend_from_slice(env.feed.as_ref());
+    data.extend_from_slice(env.oracle_id.as_ref());
+    data.extend_from_slice(&USD_PRICE.to_le_bytes());
+    data.extend_from_slice(&MAX_AGE.to_le_bytes());
+    data.extend_from_slice(&MAX_CONF_BPS.to_le_bytes());
+
+    Instruction::new_with_bytes(env.program_id, &data, vec![
+        AccountMeta::new(env.fund_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+    ])
+}
+
+
+async fn publish_price(banks_client : &mut BanksClient, payer : &Keypair, env : &TestEnv, feed : PriceFeed) {
+
+    let mut data = vec![0; PriceFeed::LEN];
+    PriceFeed::pack(feed, &mut data).unwrap();
+
+    let ix = Instruction::new_with_bytes(env.oracle_id, &data, vec![AccountMeta::new(env.feed, false)]);
+
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+}
+
+
+fn custom_error(err : TransportError) -> Option<u32> {
+
+    match err {
+        TransportError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(c))) => Some(c),
+        _ => None,
+    }
+}
+
+
+#[tokio::test]
+async fn test_set_oracle_pricing() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = set_oracle_pricing_instruction(&env, &env.manager.pubkey());
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.manager], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.pricing_mode, PricingMode::Oracle);
+    assert_eq!(fp.curve_param, USD_PRICE);
+    assert_eq!(fp.price_oracle, env.feed);
+    assert_eq!(fp.oracle_program, env.oracle_id);
+    assert_eq!(fp.oracle_max_age, MAX_AGE);
+    assert_eq!(fp.oracle_max_conf_bps, MAX_CONF_BPS);
+}
+
+
+#[tokio::test]
+async fn test_set_oracle_pricing_by_non_manager() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = set_oracle_pricing_instruction(&env, &payer.pubkey());
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::InvalidManagerAccount as u32));
+}
+
+
+#[tokio::test]
+async fn test_oracle_purchase_cost() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = set_oracle_pricing_instruction(&env, &env.manager.pubkey());
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.manager], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let now = banks_client.get_clock().await.unwrap().unix_timestamp;
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now,
+    }).await;
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+    let feed = banks_client.get_packed_account_data::<PriceFeed>(env.feed).await.unwrap();
+
+    assert_eq!(feed.check(now, fp.oracle_max_age, fp.oracle_max_conf_bps), Ok(true));
+
+    // 10 tokens at $1.5 is $15, a tenth of a SOL at $150
+    assert_eq!(fp.oracle_purchase_cost(10, &feed), Some(100_000_000));
+
+    // the other pricing modes don't apply
+    assert_eq!(fp.purchase_cost(10, now), None);
+}
+
+
+#[tokio::test]
+async fn test_oracle_price_refused() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, _) = program_test(&env).start().await;
+
+    let now = banks_client.get_clock().await.unwrap().unix_timestamp;
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now - MAX_AGE - 1,
+    }).await;
+
+    let feed = banks_client.get_packed_account_data::<PriceFeed>(env.feed).await.unwrap();
+
+    assert_eq!(feed.check(now, MAX_AGE, MAX_CONF_BPS), Err(PoolError::StalePrice));
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now + 1,
+    }).await;
+
+    let feed = banks_client.get_packed_account_data::<PriceFeed>(env.feed).await.unwrap();
+
+    assert_eq!(feed.check(now, MAX_AGE, MAX_CONF_BPS), Err(PoolError::InvalidOracleAccount));
+
+    // a confidence of 2% of the price
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 300_000_000,
+        publish_time : now,
+    }).await;
+
+    let feed = banks_client.get_packed_account_data::<PriceFeed>(env.feed).await.unwrap();
+
+    assert_eq!(feed.check(now, MAX_AGE, MAX_CONF_BPS), Err(PoolError::PriceUncertain));
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : 0,
+        expo : SOL_EXPO,
+        conf : 0,
+        publish_time : now,
+    }).await;
+
+    let feed = banks_client.get_packed_account_data::<PriceFeed>(env.feed).await.unwrap();
+
+    assert_eq!(feed.check(now, MAX_AGE, MAX_CONF_BPS), Err(PoolError::InvalidOracleAccount));
+}
+
+
+/*
+The accounts of an AddInvestor to an oracle priced pool
+minting its tokens on purchase
+*/
+struct PurchaseEnv {
+
+    investor_account : Pubkey,
+
+    user_pool : Pubkey,
+
+    pool_pda : Pubkey,
+
+    vault : Pubkey,
+
+    vault_pda : Pubkey,
+
+    mint : Pubkey,
+
+    treasury : Pubkey,
+}
+
+
+fn purchase_test(env : &TestEnv, oracle_program : Pubkey) -> (ProgramTest, PurchaseEnv) {
+
+    let mut pt = oracle_program_test(env);
+
+    let vault = Pubkey::new_unique();
+    let (vault_pda, _) = Pubkey::find_program_address(&[vault.as_ref()], &env.program_id);
+
+    let pe = PurchaseEnv {
+        investor_account : Pubkey::new_unique(),
+        user_pool : Pubkey::new_unique(),
+        pool_pda : Pubkey::new_unique(),
+        vault,
+        vault_pda,
+        mint : Pubkey::new_unique(),
+        treasury : Pubkey::new_unique(),
+    };
+
+    let mut fp = FundPool::new(true);
+    fp.manager = env.manager.pubkey();
+    fp.address = env.fund_pool;
+    fp.pool_pda = pe.pool_pda;
+    fp.token_mint = pe.mint;
+    fp.token_account = pe.vault;
+    fp.token_pda = pe.vault_pda;
+    fp.mint_mode = MintMode::Lazy;
+    fp.token_count = 1000;
+    fp.rm_token_count = 1000;
+    // the referrer's accounts follow the price feed
+    fp.referral_bps = 500;
+    fp.set_oracle_pricing(env.feed, oracle_program, USD_PRICE, MAX_AGE, MAX_CONF_BPS).unwrap();
+
+    add_fund_pool(&mut pt, env, fp);
+
+    pt.add_account(pe.investor_account, Account {
+        lamports : 1_000_000_000,
+        data : vec![0; Investor::LEN],
+        owner : env.program_id,
+        ..Account::default()
+    });
+
+    pt.add_account(pe.user_pool, Account {
+        lamports : 1_000_000_000,
+        data : vec![0; UserPool::LEN],
+        owner : env.program_id,
+        ..Account::default()
+    });
+
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(spl_token::state::Mint {
+        mint_authority : COption::Some(pe.vault_pda),
+        supply : 0,
+        decimals : 0,
+        is_initialized : true,
+        freeze_authority : COption::None,
+    }, &mut data).unwrap();
+
+    pt.add_account(pe.mint, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : spl_token::id(),
+        ..Account::default()
+    });
+
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(spl_token::state::Account {
+        mint : pe.mint,
+        owner : pe.vault_pda,
+        state : spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    }, &mut data).unwrap();
+
+    pt.add_account(pe.vault, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : spl_token::id(),
+        ..Account::default()
+    });
+
+    (pt, pe)
+}
+
+
+fn add_investor_instruction(env : &TestEnv, pe : &PurchaseEnv, investor : &Pubkey, 
+    token_count : u64, amount : u64) -> Instruction {
+
+    let mut data = vec![MODULE_INVESTOR, ACTION_ADD_INVESTOR];
+    data.extend_from_slice(investor.as_ref());
+    data.extend_from_slice(env.fund_pool.as_ref());
+    data.extend_from_slice(pe.investor_account.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&token_count.to_le_bytes());
+
+    let (config, _) = Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &env.program_id);
+
+    let (investment, _) = Pubkey::find_program_address(
+        &[INVESTMENT_SEED, pe.investor_account.as_ref(), &0u64.to_le_bytes()], &env.program_id);
+
+    Instruction::new_with_bytes(env.program_id, &data, vec![
+        AccountMeta::new(pe.investor_account, false),
+        AccountMeta::new(pe.user_pool, false),
+        AccountMeta::new(env.fund_pool, false),
+        AccountMeta::new(pe.pool_pda, false),
+        AccountMeta::new(*investor, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(env.manager.pubkey(), false),
+        AccountMeta::new_readonly(pe.vault_pda, false),
+        AccountMeta::new(get_associated_token_address(investor, &pe.mint), false),
+        AccountMeta::new(pe.vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(pe.treasury, false),
+        AccountMeta::new(pe.mint, false),
+        AccountMeta::new_readonly(*investor, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new(investment, false),
+        // the optional accounts, the price feed first then the 
+        // referrer, the investor itself here, and its referral account
+        AccountMeta::new_readonly(env.feed, false),
+        AccountMeta::new(*investor, false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        // the config account is always the last one
+        AccountMeta::new_readonly(config, false),
+    ])
+}
+
+
+#[tokio::test]
+async fn test_add_investor_at_oracle_price() {
+
+    let env = test_env();
+
+    let (pt, pe) = purchase_test(&env, env.oracle_id);
+
+    let (mut banks_client, payer, recent_blockhash) = pt.start().await;
+
+    let now = banks_client.get_clock().await.unwrap().unix_timestamp;
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now,
+    }).await;
+
+    // 10 tokens at $1.5 is a tenth of a SOL at $150
+    let ix = add_investor_instruction(&env, &pe, &payer.pubkey(), 10, 100_000_000);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_pda = banks_client.get_account(pe.pool_pda).await.unwrap().unwrap();
+
+    assert_eq!(pool_pda.lamports, 100_000_000);
+
+    let ata = get_associated_token_address(&payer.pubkey(), &pe.mint);
+    let tokens = banks_client.get_packed_account_data::<spl_token::state::Account>(ata).await.unwrap();
+
+    assert_eq!(tokens.amount, 10);
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.rm_token_count, 990);
+}
+
+
+#[tokio::test]
+async fn test_add_investor_at_stale_price() {
+
+    let env = test_env();
+
+    let (pt, pe) = purchase_test(&env, env.oracle_id);
+
+    let (mut banks_client, payer, recent_blockhash) = pt.start().await;
+
+    let now = banks_client.get_clock().await.unwrap().unix_timestamp;
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now - MAX_AGE - 1,
+    }).await;
+
+    let ix = add_investor_instruction(&env, &pe, &payer.pubkey(), 10, 100_000_000);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::StalePrice as u32));
+}
+
+
+#[tokio::test]
+async fn test_add_investor_with_feed_of_other_program() {
+
+    let env = test_env();
+
+    // the pool expects the feed of another oracle program
+    let (pt, pe) = purchase_test(&env, Pubkey::new_unique());
+
+    let (mut banks_client, payer, recent_blockhash) = pt.start().await;
+
+    let now = banks_client.get_clock().await.unwrap().unix_timestamp;
+
+    publish_price(&mut banks_client, &payer, &env, PriceFeed {
+        price : SOL_PRICE,
+        expo : SOL_EXPO,
+        conf : 10_000_000,
+        publish_time : now,
+    }).await;
+
+    let ix = add_investor_instruction(&env, &pe, &payer.pubkey(), 10, 100_000_000);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::InvalidOracleAccount as u32));
+}
@@ -0,0 +1,327 @@
+/**
+ * Filling a buy order, the lamports escrowed in the order
+ * account pay the taker for the tokens and the position moves
+ * from the taker's investor record to the maker's
+ */
+use {
+    solafund2::{
+        error::PoolError,
+        processor::process_instruction,
+        state::{FundPool, Investor, Order, OrderSide, PROGRAM_CONFIG_SEED},
+    },
+    solana_program::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    },
+    solana_program_test::{processor, tokio, ProgramTest},
+    solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+};
+
+
+const MODULE_ORDER : u8 = 5;
+
+const ACTION_FILL : u8 = 56;
+
+const PRICE : u64 = 1000;
+
+const ORDER_RENT : u64 = 1_000_000_000;
+
+
+struct TestEnv {
+
+    program_id : Pubkey,
+
+    fund_pool : Pubkey,
+
+    mint : Pubkey,
+
+    token_pda : Pubkey,
+
+    order : Pubkey,
+
+    maker : Pubkey,
+
+    maker_investor_account : Pubkey,
+
+    maker_token_account : Pubkey,
+
+    taker : Keypair,
+
+    taker_investor_account : Pubkey,
+
+    taker_token_account : Pubkey,
+}
+
+
+fn test_env() -> TestEnv {
+
+    TestEnv {
+        program_id : solafund2::id(),
+        fund_pool : Pubkey::new_unique(),
+        mint : Pubkey::new_unique(),
+        token_pda : Pubkey::new_unique(),
+        order : Pubkey::new_unique(),
+        maker : Pubkey::new_unique(),
+        maker_investor_account : Pubkey::new_unique(),
+        maker_token_account : Pubkey::new_unique(),
+        taker : Keypair::new(),
+        taker_investor_account : Pubkey::new_unique(),
+        taker_token_account : Pubkey::new_unique(),
+    }
+}
+
+
+fn add_investor(pt : &mut ProgramTest, env : &TestEnv, wallet : Pubkey, address : Pubkey,
+    token_count : u64, amount : u64) {
+
+    let mut i = Investor::new();
+    i.investor = wallet;
+    i.pool_address = env.fund_pool;
+    i.address = address;
+    i.amount = amount;
+    i.token_count = token_count;
+
+    let mut data = vec![0; Investor::LEN];
+    Investor::pack(i, &mut data).unwrap();
+
+    pt.add_account(address, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : env.program_id,
+        ..Account::default()
+    });
+}
+
+
+fn add_token_account(pt : &mut ProgramTest, env : &TestEnv, address : Pubkey, owner : Pubkey, amount : u64) {
+
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(spl_token::state::Account {
+        mint : env.mint,
+        owner,
+        amount,
+        state : spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    }, &mut data).unwrap();
+
+    pt.add_account(address, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : spl_token::id(),
+        ..Account::default()
+    });
+}
+
+
+/*
+The maker bids for 10 tokens at PRICE, the taker holds 10 tokens
+bought at 500 lamports each
+*/
+fn program_test(env : &TestEnv) -> ProgramTest {
+
+    let mut pt = ProgramTest::new("solafund2", env.program_id, processor!(process_instruction));
+
+    let mut fp = FundPool::new(true);
+    fp.manager = Pubkey::new_unique();
+    fp.address = env.fund_pool;
+    fp.token_mint = env.mint;
+    fp.token_pda = env.token_pda;
+    fp.token_count = 1000;
+    fp.rm_token_count = 990;
+    fp.token_to_lamport_ratio = 500;
+    fp.set_investor_token_count(env.taker.pubkey(), env.taker_investor_account, 10).unwrap();
+
+    let mut data = vec![0; FundPool::LEN];
+    FundPool::pack(fp, &mut data).unwrap();
+
+    pt.add_account(env.fund_pool, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : env.program_id,
+        ..Account::default()
+    });
+
+    add_investor(&mut pt, env, env.maker, env.maker_investor_account, 0, 0);
+    add_investor(&mut pt, env, env.taker.pubkey(), env.taker_investor_account, 10, 5_000);
+
+    let mut o = Order::new();
+    o.is_initialized = true;
+    o.fund_pool = env.fund_pool;
+    o.owner = env.maker;
+    o.investor_account = env.maker_investor_account;
+    o.token_account = env.maker_token_account;
+    o.side = OrderSide::Buy;
+    o.price = PRICE;
+    o.token_count = 10;
+
+    let mut data = vec![0; Order::LEN];
+    Order::pack(o, &mut data).unwrap();
+
+    pt.add_account(env.order, Account {
+        lamports : ORDER_RENT + 10 * PRICE,
+        data,
+        owner : env.program_id,
+        ..Account::default()
+    });
+
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(spl_token::state::Mint {
+        mint_authority : COption::Some(env.token_pda),
+        supply : 10,
+        decimals : 0,
+        is_initialized : true,
+        freeze_authority : COption::None,
+    }, &mut data).unwrap();
+
+    pt.add_account(env.mint, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : spl_token::id(),
+        ..Account::default()
+    });
+
+    add_token_account(&mut pt, env, env.maker_token_account, env.maker, 0);
+    add_token_account(&mut pt, env, env.taker_token_account, env.taker.pubkey(), 10);
+
+    pt
+}
+
+
+fn fill_order_instruction(env : &TestEnv, token_count : u64) -> Instruction {
+
+    let mut data = vec![MODULE_ORDER, ACTION_FILL];
+    data.extend_from_slice(&token_count.to_le_bytes());
+
+    let (config, _) = Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &env.program_id);
+
+    Instruction::new_with_bytes(env.program_id, &data, vec![
+        AccountMeta::new(env.order, false),
+        AccountMeta::new(env.fund_pool, false),
+        AccountMeta::new(env.taker.pubkey(), true),
+        AccountMeta::new(env.maker, false),
+        AccountMeta::new(env.maker_investor_account, false),
+        AccountMeta::new(env.maker_token_account, false),
+        AccountMeta::new(env.taker_investor_account, false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new(env.taker_token_account, false),
+        AccountMeta::new_readonly(env.mint, false),
+        AccountMeta::new_readonly(env.token_pda, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        // the config account is always the last one
+        AccountMeta::new_readonly(config, false),
+    ])
+}
+
+
+fn custom_error(err : TransportError) -> Option<u32> {
+
+    match err {
+        TransportError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(c))) => Some(c),
+        _ => None,
+    }
+}
+
+
+#[tokio::test]
+async fn test_fill_buy_order_in_parts() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = fill_order_instruction(&env, 4);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.taker], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(banks_client.get_balance(env.taker.pubkey()).await.unwrap(), 4 * PRICE);
+
+    let o = banks_client.get_packed_account_data::<Order>(env.order).await.unwrap();
+
+    assert_eq!(o.token_count, 6);
+
+    let tokens = banks_client.get_packed_account_data::<spl_token::state::Account>(env.maker_token_account).await.unwrap();
+
+    assert_eq!(tokens.amount, 4);
+
+    // the position moves with its part of the amount paid into the pool
+    let maker = banks_client.get_packed_account_data::<Investor>(env.maker_investor_account).await.unwrap();
+    let taker = banks_client.get_packed_account_data::<Investor>(env.taker_investor_account).await.unwrap();
+
+    assert_eq!((maker.token_count, maker.amount), (4, 2_000));
+    assert_eq!((taker.token_count, taker.amount), (6, 3_000));
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.investor_count(), 2);
+
+    // the rest closes the order to the maker
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+
+    let ix = fill_order_instruction(&env, 6);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.taker], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(banks_client.get_balance(env.taker.pubkey()).await.unwrap(), 10 * PRICE);
+    assert_eq!(banks_client.get_balance(env.maker).await.unwrap(), ORDER_RENT);
+    assert!(banks_client.get_account(env.order).await.unwrap().is_none());
+
+    let taker = banks_client.get_packed_account_data::<Investor>(env.taker_investor_account).await.unwrap();
+
+    assert_eq!((taker.token_count, taker.amount), (0, 0));
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.investor_count(), 1);
+}
+
+
+#[tokio::test]
+async fn test_fill_more_than_the_order() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = fill_order_instruction(&env, 11);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.taker], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::InvalidOrderParameters as u32));
+}
+
+
+#[tokio::test]
+async fn test_fill_with_token_account_of_other_owner() {
+
+    let env = test_env();
+
+    let mut pt = program_test(&env);
+
+    // the maker's order pays for the tokens into somebody else's account
+    add_token_account(&mut pt, &env, env.maker_token_account, Pubkey::new_unique(), 0);
+
+    let (mut banks_client, payer, recent_blockhash) = pt.start().await;
+
+    let ix = fill_order_instruction(&env, 4);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.taker], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::InvalidTokenAccount as u32));
+}
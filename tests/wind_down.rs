@@ -0,0 +1,240 @@
+/**
+ * Winding down a pool refunds its investors from the pool_pda
+ * without their signatures
+ */
+use {
+    solafund2::{
+        error::PoolError,
+        processor::process_instruction,
+        state::{FundPool, Investor, PROGRAM_CONFIG_SEED},
+    },
+    solana_program::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    },
+    solana_program_test::{processor, tokio, ProgramTest},
+    solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+};
+
+
+const MODULE_FUND_POOL : u8 = 1;
+
+const ACTION_WIND_DOWN : u8 = 45;
+
+const POOL_PDA_LAMPORTS : u64 = 10_000_000;
+
+
+struct TestEnv {
+
+    program_id : Pubkey,
+
+    fund_pool : Pubkey,
+
+    pool_pda : Pubkey,
+
+    manager : Keypair,
+
+    // the investor records and their wallets
+    investors : Vec<(Pubkey, Pubkey)>,
+}
+
+
+fn test_env() -> TestEnv {
+
+    let program_id = solafund2::id();
+    let fund_pool = Pubkey::new_unique();
+    let (pool_pda, _) = Pubkey::find_program_address(&[fund_pool.as_ref()], &program_id);
+
+    TestEnv {
+        program_id,
+        fund_pool,
+        pool_pda,
+        manager : Keypair::new(),
+        investors : vec![
+            (Pubkey::new_unique(), Pubkey::new_unique()),
+            (Pubkey::new_unique(), Pubkey::new_unique()),
+        ],
+    }
+}
+
+
+/*
+A pool of two investors, who paid 1000 lamports a token
+*/
+fn program_test(env : &TestEnv) -> ProgramTest {
+
+    let mut pt = ProgramTest::new("solafund2", env.program_id, processor!(process_instruction));
+
+    let mut fp = FundPool::new(true);
+    fp.manager = env.manager.pubkey();
+    fp.address = env.fund_pool;
+    fp.pool_pda = env.pool_pda;
+    fp.token_count = 1000;
+    fp.rm_token_count = 970;
+    fp.token_to_lamport_ratio = 1000;
+
+    for (n, (address, wallet)) in env.investors.iter().enumerate() {
+
+        let token_count = 10 * (n as u64 + 1);
+
+        fp.set_investor_token_count(*wallet, *address, token_count).unwrap();
+
+        let mut i = Investor::new();
+        i.investor = *wallet;
+        i.pool_address = env.fund_pool;
+        i.address = *address;
+        i.amount = token_count * 1000;
+        i.token_count = token_count;
+
+        let mut data = vec![0; Investor::LEN];
+        Investor::pack(i, &mut data).unwrap();
+
+        pt.add_account(*address, Account {
+            lamports : 1_000_000_000,
+            data,
+            owner : env.program_id,
+            ..Account::default()
+        });
+    }
+
+    let mut data = vec![0; FundPool::LEN];
+    FundPool::pack(fp, &mut data).unwrap();
+
+    pt.add_account(env.fund_pool, Account {
+        lamports : 1_000_000_000,
+        data,
+        owner : env.program_id,
+        ..Account::default()
+    });
+
+    pt.add_account(env.pool_pda, Account {
+        lamports : POOL_PDA_LAMPORTS,
+        ..Account::default()
+    });
+
+    pt
+}
+
+
+fn wind_down_instruction(env : &TestEnv, signer : &Pubkey, investors : &[(Pubkey, Pubkey)]) -> Instruction {
+
+    let (config, _) = Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &env.program_id);
+
+    let mut accounts = vec![
+        AccountMeta::new(env.fund_pool, false),
+        AccountMeta::new(env.pool_pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(Pubkey::default(), false),
+        AccountMeta::new_readonly(Pubkey::default(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*signer, true),
+    ];
+
+    // neither vesting nor freezable, the token accounts aren't touched
+    for (address, wallet) in investors {
+
+        accounts.push(AccountMeta::new(*address, false));
+        accounts.push(AccountMeta::new(*wallet, false));
+        accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+    }
+
+    // the config account is always the last one
+    accounts.push(AccountMeta::new_readonly(config, false));
+
+    Instruction::new_with_bytes(env.program_id, &[MODULE_FUND_POOL, ACTION_WIND_DOWN, 1], accounts)
+}
+
+
+fn custom_error(err : TransportError) -> Option<u32> {
+
+    match err {
+        TransportError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(c))) => Some(c),
+        _ => None,
+    }
+}
+
+
+#[tokio::test]
+async fn test_wind_down_refunds_investors() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = wind_down_instruction(&env, &env.manager.pubkey(), &env.investors);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.manager], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    for (n, (address, wallet)) in env.investors.iter().enumerate() {
+
+        let refund = banks_client.get_balance(*wallet).await.unwrap();
+
+        assert_eq!(refund, 10_000 * (n as u64 + 1));
+
+        let i = banks_client.get_packed_account_data::<Investor>(*address).await.unwrap();
+
+        assert_eq!(i.investor, Pubkey::default());
+        assert_eq!(i.amount, 0);
+    }
+
+    let pool_pda = banks_client.get_balance(env.pool_pda).await.unwrap();
+
+    assert_eq!(pool_pda, POOL_PDA_LAMPORTS - 30_000);
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.investor_count(), 0);
+}
+
+
+#[tokio::test]
+async fn test_wind_down_in_parts() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = wind_down_instruction(&env, &env.manager.pubkey(), &env.investors[1..]);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer, &env.manager], recent_blockhash);
+
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let fp = banks_client.get_packed_account_data::<FundPool>(env.fund_pool).await.unwrap();
+
+    assert_eq!(fp.investor_count(), 1);
+
+    assert_eq!(banks_client.get_balance(env.investors[0].1).await.unwrap(), 0);
+    assert_eq!(banks_client.get_balance(env.investors[1].1).await.unwrap(), 20_000);
+}
+
+
+#[tokio::test]
+async fn test_wind_down_by_non_manager() {
+
+    let env = test_env();
+
+    let (mut banks_client, payer, recent_blockhash) = program_test(&env).start().await;
+
+    let ix = wind_down_instruction(&env, &payer.pubkey(), &env.investors);
+
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+
+    assert_eq!(custom_error(err), Some(PoolError::InvalidManagerAccount as u32));
+
+    for (_, wallet) in env.investors.iter() {
+
+        assert_eq!(banks_client.get_balance(*wallet).await.unwrap(), 0);
+    }
+}